@@ -0,0 +1,240 @@
+use ecoblock_core::SensorData;
+use std::{fmt, str::FromStr};
+
+/// Reading fields a block filter can compare, by the name used in filters.
+const FIELDS: [(&str, Field); 5] = [
+    ("pm25", Field::Pm25),
+    ("co2", Field::Co2),
+    ("temperature", Field::Temperature),
+    ("humidity", Field::Humidity),
+    ("timestamp", Field::Timestamp),
+];
+
+/// Comparison operators, two-character ones first so `>=` isn't read as `>`.
+const COMPARISONS: [(&str, Comparison); 6] = [
+    (">=", Comparison::GreaterOrEqual),
+    ("<=", Comparison::LessOrEqual),
+    ("==", Comparison::Equal),
+    ("!=", Comparison::NotEqual),
+    (">", Comparison::Greater),
+    ("<", Comparison::Less),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Pm25,
+    Co2,
+    Temperature,
+    Humidity,
+    Timestamp,
+}
+
+impl Field {
+    fn value(self, reading: &SensorData) -> f64 {
+        match self {
+            Field::Pm25 => reading.pm25 as f64,
+            Field::Co2 => reading.co2 as f64,
+            Field::Temperature => reading.temperature as f64,
+            Field::Humidity => reading.humidity as f64,
+            Field::Timestamp => reading.timestamp as f64,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        FIELDS
+            .iter()
+            .find(|&&(_, field)| field == self)
+            .map_or("", |&(name, _)| name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    fn holds(self, left: f64, right: f64) -> bool {
+        match self {
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        COMPARISONS
+            .iter()
+            .find(|&&(_, comparison)| comparison == self)
+            .map_or("", |&(symbol, _)| symbol)
+    }
+}
+
+/// A predicate on a block's sensor reading, such as `temperature > 30`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Condition {
+    field: Field,
+    comparison: Comparison,
+    value: f64,
+}
+
+impl Condition {
+    pub fn holds(&self, reading: &SensorData) -> bool {
+        self.comparison.holds(self.field.value(reading), self.value)
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(condition: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid filter '{}', expected <field> <operator> <number>",
+                condition
+            )
+        };
+        let start = condition.find(['<', '>', '=', '!']).ok_or_else(invalid)?;
+        let (name, rest) = condition.split_at(start);
+        let (comparison, value) = COMPARISONS
+            .iter()
+            .find_map(|&(symbol, comparison)| Some((comparison, rest.strip_prefix(symbol)?)))
+            .ok_or_else(invalid)?;
+        let field = FIELDS
+            .iter()
+            .find(|&&(field_name, _)| field_name == name.trim())
+            .map(|&(_, field)| field)
+            .ok_or_else(|| format!("Unknown filter field '{}'", name.trim()))?;
+        let value: f64 = value
+            .trim()
+            .parse()
+            .ok()
+            .filter(|value: &f64| value.is_finite())
+            .ok_or_else(invalid)?;
+
+        Ok(Self {
+            field,
+            comparison,
+            // Readings are f32, so `temperature == 21.3` must compare
+            // against 21.3 rounded the same way.
+            value: match field {
+                Field::Timestamp => value,
+                _ => value as f32 as f64,
+            },
+        })
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} ", self.field.name(), self.comparison.symbol())?;
+        match self.field {
+            Field::Timestamp => write!(f, "{}", self.value),
+            _ => write!(f, "{}", self.value as f32),
+        }
+    }
+}
+
+/// Conditions a block's reading must all meet before the block is sent to a
+/// WebSocket connection. An empty filter lets every block through.
+#[derive(Debug, Clone, Default)]
+pub struct BlockFilter {
+    conditions: Vec<Condition>,
+}
+
+impl BlockFilter {
+    pub fn parse(conditions: &[String]) -> Result<Self, String> {
+        Ok(Self {
+            conditions: conditions
+                .iter()
+                .map(|condition| condition.parse())
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn matches(&self, reading: &SensorData) -> bool {
+        self.conditions
+            .iter()
+            .all(|condition| condition.holds(reading))
+    }
+
+    /// The conditions in their normalized form, e.g. `temperature > 30`.
+    pub fn conditions(&self) -> Vec<String> {
+        self.conditions
+            .iter()
+            .map(|condition| condition.to_string())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading() -> SensorData {
+        SensorData {
+            pm25: 12.5,
+            co2: 410.0,
+            temperature: 21.3,
+            humidity: 55.0,
+            timestamp: 1_700_000_000_000,
+        }
+    }
+
+    fn holds(condition: &str) -> bool {
+        condition.parse::<Condition>().unwrap().holds(&reading())
+    }
+
+    #[test]
+    fn parses_every_operator() {
+        assert!(holds("pm25 > 12"));
+        assert!(!holds("pm25 > 12.5"));
+        assert!(holds("pm25 >= 12.5"));
+        assert!(holds("co2 < 411"));
+        assert!(holds("co2 <= 410"));
+        assert!(holds("humidity == 55"));
+        assert!(holds("humidity != 54"));
+        assert!(holds("timestamp>=1700000000000"));
+    }
+
+    #[test]
+    fn rounds_values_like_f32_readings() {
+        assert!(holds("temperature == 21.3"));
+        assert!(!holds("temperature != 21.3"));
+        assert!(!holds("temperature > 21.3"));
+    }
+
+    #[test]
+    fn normalizes_conditions() {
+        let filter =
+            BlockFilter::parse(&["temperature>30".to_string(), " pm25 <= 35.5 ".to_string()])
+                .unwrap();
+        assert_eq!(filter.conditions(), ["temperature > 30", "pm25 <= 35.5"]);
+    }
+
+    #[test]
+    fn rejects_unknown_fields_and_bad_values() {
+        let error = "weather > 3".parse::<Condition>().unwrap_err();
+        assert_eq!(error, "Unknown filter field 'weather'");
+        assert!("pm25 > warm".parse::<Condition>().is_err());
+        assert!("pm25 > inf".parse::<Condition>().is_err());
+        assert!("pm25 30".parse::<Condition>().is_err());
+        assert!("pm25 => 30".parse::<Condition>().is_err());
+    }
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        assert!(BlockFilter::default().matches(&reading()));
+        let filter =
+            BlockFilter::parse(&["pm25 > 10".to_string(), "co2 > 500".to_string()]).unwrap();
+        assert!(!filter.matches(&reading()));
+    }
+}
@@ -0,0 +1,127 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::path::PathBuf;
+
+use crate::models::{Scenario, TimeScale};
+use crate::simulation::{RunStore, ScenarioFormat, SimulationManager};
+
+const SIMULATE_USAGE: &str = "usage: ecoblock-api simulate <config.json|scenario.toml> \
+[--out <dir>] [--time-scale <factor|max>] [--seed <seed>]";
+
+struct SimulateArgs {
+    file: PathBuf,
+    out: Option<PathBuf>,
+    time_scale: Option<TimeScale>,
+    seed: Option<u64>,
+}
+
+impl SimulateArgs {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut file = None;
+        let mut out = None;
+        let mut time_scale = None;
+        let mut seed = None;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |flag: &str| {
+                args.next()
+                    .ok_or_else(|| anyhow!("{} needs a value\n{}", flag, SIMULATE_USAGE))
+            };
+            match arg.as_str() {
+                "--out" => out = Some(PathBuf::from(value("--out")?)),
+                "--time-scale" => {
+                    let scale = value("--time-scale")?;
+                    time_scale = Some(match scale.as_str() {
+                        "max" => TimeScale::AsFastAsPossible,
+                        factor => TimeScale::Factor {
+                            factor: factor
+                                .parse()
+                                .with_context(|| format!("invalid time scale '{}'", factor))?,
+                        },
+                    });
+                }
+                "--seed" => {
+                    let value = value("--seed")?;
+                    seed = Some(
+                        value
+                            .parse()
+                            .with_context(|| format!("invalid seed '{}'", value))?,
+                    );
+                }
+                flag if flag.starts_with("--") => {
+                    bail!("unknown option {}\n{}", flag, SIMULATE_USAGE)
+                }
+                _ if file.is_none() => file = Some(PathBuf::from(&arg)),
+                _ => bail!("unexpected argument {}\n{}", arg, SIMULATE_USAGE),
+            }
+        }
+
+        Ok(Self {
+            file: file.ok_or_else(|| anyhow!(SIMULATE_USAGE))?,
+            out,
+            time_scale,
+            seed,
+        })
+    }
+}
+
+/// Runs the `simulate` subcommand: runs a simulation config or scenario file
+/// to completion on the same engine as the HTTP API and writes its record,
+/// report and event log to the output directory. `--time-scale` overrides
+/// the file's `time_scale`; Ctrl-C stops the run early and still writes its
+/// artifacts.
+pub async fn simulate(args: impl IntoIterator<Item = String>) -> Result<()> {
+    let args = SimulateArgs::parse(args)?;
+    let document = std::fs::read_to_string(&args.file)
+        .with_context(|| format!("failed to read {}", args.file.display()))?;
+    let format = match args.file.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => ScenarioFormat::Toml,
+        _ => ScenarioFormat::Json,
+    };
+    let mut scenario = Scenario::parse(&document, format)?;
+    if let Some(time_scale) = args.time_scale {
+        scenario.config.time_scale = time_scale;
+    }
+    if args.seed.is_some() {
+        scenario.config.seed = args.seed;
+    }
+
+    let store = match &args.out {
+        Some(out) => RunStore::new(out),
+        None => RunStore::from_env(),
+    };
+    let manager = SimulationManager::with_store(store.clone(), usize::MAX);
+    let run_id = manager.run_scenario(scenario)?;
+    println!("Running simulation {}", run_id);
+
+    tokio::select! {
+        result = manager.wait(&run_id) => result?,
+        _ = tokio::signal::ctrl_c() => {
+            println!("Stopping simulation {}", run_id);
+            // The run may finish on its own in the meantime.
+            let _ = manager.stop(&run_id);
+            manager.wait(&run_id).await?;
+        }
+    }
+
+    let report = manager
+        .report(&run_id)?
+        .ok_or_else(|| anyhow!("simulation {} did not write a report", run_id))?;
+    println!(
+        "{} {} blocks on {} nodes in {} ms of virtual time",
+        if report.completed {
+            "Completed"
+        } else {
+            "Stopped after"
+        },
+        report.blocks_created,
+        report.config.node_count,
+        report.virtual_duration_ms
+    );
+    println!("Event log digest {}", report.event_log_digest);
+    println!(
+        "Wrote report and event log to {}",
+        store.dir().join(&run_id).display()
+    );
+    Ok(())
+}
@@ -1,46 +1,69 @@
 use axum::{
-    extract::{Path, State, WebSocketUpgrade},
-    http::StatusCode,
-    response::{Json, Response},
-    routing::{get, post},
+    extract::{Path, Query, State, WebSocketUpgrade},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{delete, get, post},
     Router,
 };
 use serde_json::json;
+use std::net::SocketAddr;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
+use crate::middleware::{require_admin, AuthConfig};
 use crate::models::*;
-use crate::websocket::WebSocketManager;
-use ecoblock_core::TangleBlockData;
+use crate::peers::{spawn_ban_enforcer, spawn_peer_sampler, BanList, PeerMonitor};
+use crate::reputation::{ReputationConfig, ReputationTracker};
+use crate::simulation::{ScenarioFormat, SensorGenerator, SimulationError, SimulationManager};
+use crate::stats::spawn_stats_refresher;
+use crate::topology::{spawn_topology_refresher, TopologyCrawler};
+use crate::trace::{TraceRecorder, TraceStore};
+use crate::websocket::{
+    spawn_ban_forwarder, spawn_block_forwarder, spawn_network_updates, spawn_peer_forwarder,
+    spawn_simulation_forwarder, WebSocketManager,
+};
+use ecoblock_core::{SensorData, TangleBlockData};
 use ecoblock_crypto::keys::keypair::CryptoKeypair;
 use ecoblock_network::NetworkNode;
 use ecoblock_storage::tangle::block::TangleBlock;
 
 pub type SharedState = Arc<NetworkNode>;
 
+/// Upper bound on readings produced by one sensor data generation request.
+const MAX_GENERATED_READINGS: usize = 10_000;
+
 pub struct AppState {
     pub network_node: Arc<NetworkNode>,
     pub websocket_manager: WebSocketManager,
     pub network_stats: Arc<RwLock<crate::models::ApiNetworkStats>>,
+    pub ban_list: BanList,
+    pub peer_monitor: PeerMonitor,
+    pub reputation: ReputationTracker,
+    pub topology: TopologyCrawler,
+    pub simulation: SimulationManager,
+    pub trace_recorder: TraceRecorder,
+    pub auth: AuthConfig,
 }
 
 pub fn create_router(state: SharedState) -> Router {
     let websocket_manager = WebSocketManager::new();
+    // Placeholder until the stats refresher's first tick fills in live data.
     let network_stats = Arc::new(RwLock::new(crate::models::ApiNetworkStats {
         info: crate::models::NetworkInfo {
-            node_id: "test-node".to_string(),
+            node_id: String::new(),
             peer_count: 0,
             block_count: 0,
             uptime: 0,
-            status: "active".to_string(),
-            version: "0.1.0".to_string(),
-            network_type: "test".to_string(),
+            status: "starting".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            network_type: "mesh".to_string(),
         },
         peers: vec![],
         metrics: crate::models::NetworkMetrics {
             total_blocks: 0,
             blocks_per_minute: 0.0,
             average_latency: 0.0,
-            network_health: 1.0,
+            network_health: 0.0,
             active_peers: 0,
             total_peers: 0,
             active_connections: 0,
@@ -51,29 +74,107 @@ pub fn create_router(state: SharedState) -> Router {
         },
     }));
 
+    let ban_list = BanList::from_env();
+    spawn_ban_enforcer(state.clone(), ban_list.clone());
+    spawn_stats_refresher(state.clone(), ban_list.clone(), network_stats.clone());
+    let peer_monitor = PeerMonitor::new();
+    let reputation = ReputationTracker::new(ReputationConfig::from_env(), ban_list.clone());
+    spawn_peer_sampler(state.clone(), peer_monitor.clone(), reputation.clone());
+    let topology = TopologyCrawler::from_env();
+    spawn_topology_refresher(state.clone(), topology.clone());
+    let simulation = SimulationManager::new();
+    spawn_simulation_forwarder(simulation.clone(), websocket_manager.clone());
+    spawn_block_forwarder(state.clone(), websocket_manager.clone());
+    spawn_network_updates(network_stats.clone(), websocket_manager.clone());
+    spawn_peer_forwarder(peer_monitor.clone(), websocket_manager.clone());
+    spawn_ban_forwarder(ban_list.clone(), websocket_manager.clone());
+
     let app_state = Arc::new(AppState {
         network_node: state.clone(),
         websocket_manager,
         network_stats,
+        ban_list,
+        peer_monitor,
+        reputation,
+        topology,
+        simulation,
+        trace_recorder: TraceRecorder::new(TraceStore::from_env()),
+        auth: AuthConfig::from_env(),
     });
 
+    let admin_routes = Router::new()
+        .route("/api/network/peers", post(connect_peer))
+        .route("/api/network/peers/:id", delete(disconnect_peer))
+        .route("/api/network/peers/:id/ban", post(ban_peer))
+        .route("/api/network/peers/:id/ban", delete(unban_peer))
+        .route("/api/network/bans", get(get_bans))
+        .route("/api/traces/record", post(start_trace_recording))
+        .route("/api/traces/record/stop", post(stop_trace_recording))
+        .route_layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            require_admin,
+        ));
+
     Router::new()
         .route("/ws", get(websocket_handler))
         .route("/api/network/info", get(get_network_info))
         .route("/api/network/peers", get(get_peers))
+        .route("/api/network/peers/:id", get(get_peer_detail))
+        .route(
+            "/api/network/peers/:id/reputation",
+            get(get_peer_reputation),
+        )
         .route("/api/network/metrics", get(get_network_metrics))
+        .route("/api/network/topology", get(get_network_topology))
         .route("/api/network/stats", get(get_network_stats))
         .route("/api/blocks", get(get_blocks))
         .route("/api/blocks/:hash", get(get_block))
         .route("/api/blocks", post(create_block))
         .route("/api/blocks/:hash/send", post(send_block))
+        .route("/api/sensor-data/generate", post(generate_sensor_data))
         .route("/api/simulation/config", get(get_simulation_config))
         .route("/api/simulation/config", post(set_simulation_config))
+        .route(
+            "/api/simulation/topology/preview",
+            post(preview_simulation_topology),
+        )
         .route("/api/simulation/start", post(start_simulation))
         .route("/api/simulation/stop", post(stop_simulation))
+        .route("/api/simulation/partition", post(partition_simulation))
+        .route("/api/simulation/heal", post(heal_simulation))
         .route("/api/simulation/status", get(get_simulation_status))
+        .route("/api/simulation/runs", get(list_simulation_runs))
+        .route("/api/simulation/runs", post(start_simulation_run))
+        .route("/api/simulation/runs/:id", get(get_simulation_run))
+        .route("/api/simulation/runs/:id/stop", post(stop_simulation_run))
+        .route(
+            "/api/simulation/runs/:id/partition",
+            post(partition_simulation_run),
+        )
+        .route("/api/simulation/runs/:id/heal", post(heal_simulation_run))
+        .route(
+            "/api/simulation/runs/:id/report",
+            get(get_simulation_report),
+        )
+        .route(
+            "/api/simulation/runs/:id/events",
+            get(get_simulation_events),
+        )
+        .route("/api/simulation/compare", get(compare_simulation_runs))
+        .route("/api/simulation/sweeps", post(start_simulation_sweep))
+        .route("/api/simulation/sweeps/:id", get(get_simulation_sweep))
+        .route(
+            "/api/simulation/sweeps/:id/stop",
+            post(stop_simulation_sweep),
+        )
+        .route("/api/simulation/replay/:id", post(replay_simulation))
+        .route("/api/simulation/scenario", post(run_simulation_scenario))
+        .route("/api/traces", get(list_traces))
+        .route("/api/traces/record", get(get_trace_recording))
+        .route("/api/traces/:id", get(get_trace))
         .route("/api/health", get(health_check))
         .route("/api/version", get(get_version))
+        .merge(admin_routes)
         .with_state(app_state)
 }
 
@@ -82,10 +183,7 @@ pub async fn websocket_handler(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
 ) -> Response {
-    state
-        .websocket_manager
-        .handle_upgrade(ws, state.network_stats.clone())
-        .await
+    state.websocket_manager.handle_upgrade(ws).await
 }
 
 pub async fn get_network_info(
@@ -113,16 +211,200 @@ pub async fn get_peers(
 
     let peer_infos: Vec<PeerInfo> = peers
         .into_iter()
-        .map(|(id, info)| PeerInfo {
-            id: id.0.to_string(),
+        .map(|(id, info)| {
+            let id = id.0.to_string();
+            PeerInfo {
+                reputation: state.reputation.score(&id),
+                id,
+                address: info.address.to_string(),
+                last_seen: info.last_seen,
+                is_connected: info.is_connected,
+                latency: info.latency,
+            }
+        })
+        .collect();
+
+    Ok(Json(ApiResponse::success(peer_infos)))
+}
+
+pub async fn get_peer_detail(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(query): Query<PeerDetailQuery>,
+) -> Result<Json<ApiResponse<PeerDetail>>, StatusCode> {
+    let peers = state.network_node.peer_discovery.get_peers().await;
+    let current = peers
+        .into_iter()
+        .find(|(peer_id, _)| peer_id.0.to_string() == id)
+        .map(|(_, info)| info);
+    let history = state.peer_monitor.history(&id);
+
+    let info = match (&current, &history) {
+        (Some(info), _) => PeerInfo {
+            id: id.clone(),
             address: info.address.to_string(),
             last_seen: info.last_seen,
             is_connected: info.is_connected,
             latency: info.latency,
-        })
-        .collect();
+            reputation: state.reputation.score(&id),
+        },
+        (None, Some(history)) => PeerInfo {
+            id: id.clone(),
+            address: history.address.clone(),
+            last_seen: history
+                .latency_samples
+                .back()
+                .map(|sample| sample.timestamp.timestamp() as u64)
+                .unwrap_or(0),
+            is_connected: false,
+            latency: history
+                .latency_samples
+                .back()
+                .and_then(|sample| sample.latency),
+            reputation: state.reputation.score(&id),
+        },
+        (None, None) => return Err(StatusCode::NOT_FOUND),
+    };
 
-    Ok(Json(ApiResponse::success(peer_infos)))
+    let window = Duration::from_secs(query.window_secs.unwrap_or(300).max(1));
+    let detail = match history {
+        Some(history) => PeerDetail {
+            info,
+            latency_windows: history.latency_windows(window),
+            connection_history: history.connection_events.into_iter().collect(),
+            latency_history: history.latency_samples.into_iter().collect(),
+            blocks_sent: history.blocks_sent,
+            last_error: history.last_error,
+        },
+        None => PeerDetail {
+            info,
+            connection_history: vec![],
+            latency_history: vec![],
+            latency_windows: vec![],
+            blocks_sent: 0,
+            last_error: None,
+        },
+    };
+
+    Ok(Json(ApiResponse::success(detail)))
+}
+
+pub async fn get_peer_reputation(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<ReputationReport>>, StatusCode> {
+    match state.reputation.report(&id) {
+        Some(report) => Ok(Json(ApiResponse::success(report))),
+        None => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+pub async fn connect_peer(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<ConnectPeerRequest>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    let address: SocketAddr = request
+        .address
+        .parse()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    if state.ban_list.is_address_banned(&address.to_string()) {
+        return Ok(Json(ApiResponse::error(format!(
+            "Peer address {} is banned",
+            address
+        ))));
+    }
+
+    match state.network_node.connect_peer(address).await {
+        Ok(peer_id) => Ok(Json(ApiResponse::success(peer_id.0.to_string()))),
+        Err(e) => Ok(Json(ApiResponse::error(format!(
+            "Failed to connect to peer: {}",
+            e
+        )))),
+    }
+}
+
+pub async fn disconnect_peer(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    let peers = state.network_node.peer_discovery.get_peers().await;
+    let peer_id = peers
+        .keys()
+        .find(|peer_id| peer_id.0.to_string() == id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if let Err(e) = state.network_node.disconnect_peer(peer_id).await {
+        state
+            .peer_monitor
+            .record_error(&id, format!("Disconnect failed: {}", e));
+        return Ok(Json(ApiResponse::error(format!(
+            "Failed to disconnect peer: {}",
+            e
+        ))));
+    }
+    Ok(Json(ApiResponse::success(
+        "Peer disconnected successfully".to_string(),
+    )))
+}
+
+/// Bans a peer and disconnects it. The network node accepts inbound
+/// connections without consulting the ban list, so a banned peer that
+/// reconnects is only dropped on the ban enforcer's next pass.
+pub async fn ban_peer(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(request): Json<BanPeerRequest>,
+) -> Result<Json<ApiResponse<BanEntry>>, StatusCode> {
+    let peers = state.network_node.peer_discovery.get_peers().await;
+    let peer = peers
+        .iter()
+        .find(|(peer_id, _)| peer_id.0.to_string() == id);
+
+    let entry = match state.ban_list.ban(
+        id,
+        peer.map(|(_, info)| info.address.to_string()),
+        request.duration_secs.map(Duration::from_secs),
+        request.reason,
+    ) {
+        Ok(entry) => entry,
+        Err(e) => {
+            return Ok(Json(ApiResponse::error(format!(
+                "Failed to ban peer: {}",
+                e
+            ))))
+        }
+    };
+
+    if let Some((peer_id, info)) = peer {
+        if info.is_connected {
+            if let Err(e) = state.network_node.disconnect_peer(peer_id).await {
+                log::warn!("Failed to disconnect banned peer {}: {}", entry.peer_id, e);
+            }
+        }
+    }
+
+    Ok(Json(ApiResponse::success(entry)))
+}
+
+pub async fn unban_peer(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<BanEntry>>, StatusCode> {
+    match state.ban_list.unban(&id) {
+        Ok(Some(entry)) => Ok(Json(ApiResponse::success(entry))),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Ok(Json(ApiResponse::error(format!(
+            "Failed to unban peer: {}",
+            e
+        )))),
+    }
+}
+
+pub async fn get_bans(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<Vec<BanEntry>>>, StatusCode> {
+    Ok(Json(ApiResponse::success(state.ban_list.active())))
 }
 
 pub async fn get_network_metrics(
@@ -157,6 +439,13 @@ pub async fn get_network_metrics(
     Ok(Json(ApiResponse::success(metrics)))
 }
 
+pub async fn get_network_topology(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<NetworkTopology>>, StatusCode> {
+    // Crawls can take a while, so they only run in the background refresher.
+    Ok(Json(ApiResponse::success(state.topology.cached())))
+}
+
 pub async fn get_network_stats(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<ApiResponse<serde_json::Value>>, StatusCode> {
@@ -187,16 +476,7 @@ pub async fn get_blocks(
 ) -> Result<Json<ApiResponse<Vec<BlockInfo>>>, StatusCode> {
     let blocks = state.network_node.block_cache.read().await;
 
-    let block_infos: Vec<BlockInfo> = blocks
-        .values()
-        .map(|block| BlockInfo {
-            hash: block.id.clone(),
-            timestamp: block.data.data.timestamp,
-            sensor_data: block.data.data.clone(),
-            signature: block.signature.0.clone(),
-            parent_hashes: block.data.parents.clone(),
-        })
-        .collect();
+    let block_infos: Vec<BlockInfo> = blocks.values().map(block_info).collect();
 
     Ok(Json(ApiResponse::success(block_infos)))
 }
@@ -208,24 +488,24 @@ pub async fn get_block(
     let blocks = state.network_node.block_cache.read().await;
 
     match blocks.get(&hash) {
-        Some(block) => {
-            let block_info = BlockInfo {
-                hash: block.id.clone(),
-                timestamp: block.data.data.timestamp,
-                sensor_data: block.data.data.clone(),
-                signature: block.signature.0.clone(),
-                parent_hashes: block.data.parents.clone(),
-            };
-            Ok(Json(ApiResponse::success(block_info)))
-        }
+        Some(block) => Ok(Json(ApiResponse::success(block_info(block)))),
         None => Err(StatusCode::NOT_FOUND),
     }
 }
 
-pub async fn create_block(
-    State(state): State<Arc<AppState>>,
-    Json(request): Json<CreateBlockRequest>,
-) -> Result<Json<ApiResponse<BlockInfo>>, StatusCode> {
+pub(crate) fn block_info(block: &TangleBlock) -> BlockInfo {
+    BlockInfo {
+        hash: block.id.clone(),
+        timestamp: block.data.data.timestamp,
+        sensor_data: block.data.data.clone(),
+        signature: block.signature.0.clone(),
+        parent_hashes: block.data.parents.clone(),
+    }
+}
+
+/// Signs `sensor_data` into a new block on top of the most recent local blocks,
+/// stores it in the block cache and announces it to WebSocket clients.
+async fn insert_local_block(state: &AppState, sensor_data: SensorData) -> TangleBlock {
     let keypair = CryptoKeypair::generate();
 
     // Get parent blocks from cache (select recent tips)
@@ -249,7 +529,7 @@ pub async fn create_block(
 
     let data = TangleBlockData {
         parents,
-        data: request.sensor_data,
+        data: sensor_data,
     };
 
     let block = TangleBlock::new(data, &keypair);
@@ -258,16 +538,63 @@ pub async fn create_block(
         let mut cache = state.network_node.block_cache.write().await;
         cache.insert(block.id.clone(), block.clone());
     }
+    state
+        .websocket_manager
+        .broadcast_block_created(&block_info(&block));
 
-    let block_info = BlockInfo {
-        hash: block.id.clone(),
-        timestamp: block.data.data.timestamp,
-        sensor_data: block.data.data.clone(),
-        signature: block.signature.0.clone(),
-        parent_hashes: block.data.parents.clone(),
+    block
+}
+
+pub async fn create_block(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<CreateBlockRequest>,
+) -> Result<Json<ApiResponse<BlockInfo>>, StatusCode> {
+    let block = insert_local_block(&state, request.sensor_data).await;
+    Ok(Json(ApiResponse::success(block_info(&block))))
+}
+
+pub async fn generate_sensor_data(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<GenerateSensorDataRequest>,
+) -> Result<Json<ApiResponse<Vec<SensorData>>>, StatusCode> {
+    if let Err(e) = request.profile.validate() {
+        return Ok(Json(ApiResponse::error(e.to_string())));
+    }
+    if request.count > MAX_GENERATED_READINGS {
+        return Ok(Json(ApiResponse::error(format!(
+            "count must not exceed {}",
+            MAX_GENERATED_READINGS
+        ))));
+    }
+
+    let interval_ms = request.interval_ms.unwrap_or(60_000);
+    let Some(span_ms) = interval_ms.checked_mul(request.count as u64) else {
+        return Ok(Json(ApiResponse::error(
+            "interval_ms is too large for count readings".to_string(),
+        )));
+    };
+    let start_ms = match request.start {
+        Some(start) => start.timestamp_millis().max(0) as u64,
+        None => (chrono::Utc::now().timestamp_millis().max(0) as u64).saturating_sub(span_ms),
     };
+    if start_ms.checked_add(span_ms).is_none() {
+        return Ok(Json(ApiResponse::error(
+            "readings would end past the largest timestamp".to_string(),
+        )));
+    }
+    let mut generator =
+        SensorGenerator::new(request.profile, request.seed.unwrap_or_else(rand::random));
+    let readings: Vec<SensorData> = (0..request.count as u64)
+        .map(|i| generator.sample(start_ms + i * interval_ms))
+        .collect();
+
+    if request.submit {
+        for reading in &readings {
+            insert_local_block(&state, reading.clone()).await;
+        }
+    }
 
-    Ok(Json(ApiResponse::success(block_info)))
+    Ok(Json(ApiResponse::success(readings)))
 }
 
 pub async fn send_block(
@@ -279,12 +606,34 @@ pub async fn send_block(
 
     match blocks.get(&hash) {
         Some(block) => {
+            let connected: Vec<String> = state
+                .network_node
+                .peer_discovery
+                .get_peers()
+                .await
+                .into_iter()
+                .filter(|(_, info)| info.is_connected)
+                .map(|(id, _)| id.0.to_string())
+                .collect();
+
             if let Err(e) = state.network_node.broadcast_block(block.clone()).await {
+                for peer_id in &connected {
+                    state
+                        .peer_monitor
+                        .record_error(peer_id, format!("Block broadcast failed: {}", e));
+                }
                 return Ok(Json(ApiResponse::error(format!(
                     "Failed to send block: {}",
                     e
                 ))));
             }
+            for peer_id in &connected {
+                state.peer_monitor.record_block_sent(peer_id);
+            }
+            state.websocket_manager.broadcast_network_event(
+                "block_sent",
+                json!({ "hash": hash, "peers": connected.len() }),
+            );
             Ok(Json(ApiResponse::success(
                 "Block sent successfully".to_string(),
             )))
@@ -293,41 +642,365 @@ pub async fn send_block(
     }
 }
 
-pub async fn get_simulation_config() -> Result<Json<ApiResponse<SimulationConfig>>, StatusCode> {
-    let config = SimulationConfig {
-        topology: "mesh".to_string(),
-        node_count: 10,
-        block_count: 100,
-        interval_ms: 1000,
-    };
-    Ok(Json(ApiResponse::success(config)))
+pub async fn get_simulation_config(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<SimulationConfig>>, StatusCode> {
+    Ok(Json(ApiResponse::success(state.simulation.config())))
 }
 
 pub async fn set_simulation_config(
+    State(state): State<Arc<AppState>>,
     Json(config): Json<SimulationConfig>,
 ) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    if let Err(e) = state.simulation.set_config(config) {
+        return Ok(Json(ApiResponse::error(e.to_string())));
+    }
     Ok(Json(ApiResponse::success(
         "Configuration updated".to_string(),
     )))
 }
 
-pub async fn start_simulation() -> Result<Json<ApiResponse<String>>, StatusCode> {
-    Ok(Json(ApiResponse::success("Simulation started".to_string())))
+pub async fn preview_simulation_topology(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<TopologyPreviewRequest>,
+) -> Result<Json<ApiResponse<SimulationGraph>>, StatusCode> {
+    // Generating some topologies is quadratic in the node count.
+    let max_nodes = state.simulation.max_total_nodes();
+    if request.node_count > max_nodes {
+        return Ok(Json(ApiResponse::error(format!(
+            "node_count must not exceed {}",
+            max_nodes
+        ))));
+    }
+    if let Err(e) = request.topology.validate(request.node_count) {
+        return Ok(Json(ApiResponse::error(e.to_string())));
+    }
+    let adjacency = request
+        .topology
+        .generate(request.node_count, &mut rand::thread_rng());
+    Ok(Json(ApiResponse::success(SimulationGraph::from_adjacency(
+        &adjacency,
+    ))))
 }
 
-pub async fn stop_simulation() -> Result<Json<ApiResponse<String>>, StatusCode> {
-    Ok(Json(ApiResponse::success("Simulation stopped".to_string())))
+pub async fn start_simulation(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    match state.simulation.start() {
+        Ok(run_id) => Ok(Json(ApiResponse::success(run_id))),
+        Err(e) => Ok(Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+pub async fn list_simulation_runs(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<Vec<RunSummary>>>, StatusCode> {
+    match state.simulation.runs() {
+        Ok(runs) => Ok(Json(ApiResponse::success(runs))),
+        Err(e) => {
+            log::warn!("Failed to list simulation runs: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Starts a run with the given config, alongside any runs already going.
+pub async fn start_simulation_run(
+    State(state): State<Arc<AppState>>,
+    Json(config): Json<SimulationConfig>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    match state.simulation.start_with(config) {
+        Ok(run_id) => Ok(Json(ApiResponse::success(run_id))),
+        Err(e) => Ok(Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+pub async fn get_simulation_run(
+    State(state): State<Arc<AppState>>,
+    Path(run_id): Path<String>,
+) -> Result<Json<ApiResponse<SimulationStatus>>, StatusCode> {
+    match state.simulation.run_status(&run_id) {
+        Some(status) => Ok(Json(ApiResponse::success(status))),
+        None => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+pub async fn stop_simulation_run(
+    State(state): State<Arc<AppState>>,
+    Path(run_id): Path<String>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    command_response(state.simulation.stop(&run_id), "Simulation stopped")
+}
+
+pub async fn partition_simulation_run(
+    State(state): State<Arc<AppState>>,
+    Path(run_id): Path<String>,
+    Json(request): Json<PartitionRequest>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    command_response(
+        state.simulation.partition(&run_id, request.groups),
+        "Partition applied",
+    )
+}
+
+pub async fn heal_simulation_run(
+    State(state): State<Arc<AppState>>,
+    Path(run_id): Path<String>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    command_response(state.simulation.heal(&run_id), "Partition healed")
+}
+
+/// Stops the most recently started run.
+pub async fn stop_simulation(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    let result = latest_run(&state).and_then(|run_id| state.simulation.stop(&run_id));
+    command_response(result, "Simulation stopped")
+}
+
+/// Partitions the most recently started run.
+pub async fn partition_simulation(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<PartitionRequest>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    let result =
+        latest_run(&state).and_then(|run_id| state.simulation.partition(&run_id, request.groups));
+    command_response(result, "Partition applied")
+}
+
+/// Heals the most recently started run.
+pub async fn heal_simulation(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    let result = latest_run(&state).and_then(|run_id| state.simulation.heal(&run_id));
+    command_response(result, "Partition healed")
+}
+
+/// Status of the most recently started run.
+pub async fn get_simulation_status(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<SimulationStatus>>, StatusCode> {
+    Ok(Json(ApiResponse::success(state.simulation.status())))
+}
+
+fn latest_run(state: &AppState) -> Result<String, SimulationError> {
+    state
+        .simulation
+        .latest_run_id()
+        .ok_or(SimulationError::NotRunning)
+}
+
+fn command_response(
+    result: Result<(), SimulationError>,
+    message: &str,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    match result {
+        Ok(()) => Ok(Json(ApiResponse::success(message.to_string()))),
+        Err(SimulationError::RunNotFound(_) | SimulationError::SweepNotFound(_)) => {
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => Ok(Json(ApiResponse::error(e.to_string()))),
+    }
 }
 
-pub async fn get_simulation_status() -> Result<Json<ApiResponse<SimulationStatus>>, StatusCode> {
-    let status = SimulationStatus {
-        is_running: false,
-        blocks_sent: 0,
-        blocks_received: 0,
-        start_time: None,
-        duration: None,
+/// Accepts a JSON or TOML (`Content-Type: application/toml`) scenario
+/// document, validates it and starts it unless `?dry_run=true`.
+pub async fn run_simulation_scenario(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ScenarioQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Json<ApiResponse<serde_json::Value>>, StatusCode> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    let scenario = match Scenario::parse(&body, ScenarioFormat::from_content_type(content_type)) {
+        Ok(scenario) => scenario,
+        Err(e) => return Ok(Json(ApiResponse::error(e.to_string()))),
     };
-    Ok(Json(ApiResponse::success(status)))
+
+    if query.dry_run {
+        return Ok(Json(ApiResponse::success(json!(scenario))));
+    }
+    match state.simulation.run_scenario(scenario) {
+        Ok(run_id) => Ok(Json(ApiResponse::success(json!({ "run_id": run_id })))),
+        Err(e) => Ok(Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+pub async fn replay_simulation(
+    State(state): State<Arc<AppState>>,
+    Path(run_id): Path<String>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    match state.simulation.replay(&run_id) {
+        Ok(replay_id) => Ok(Json(ApiResponse::success(replay_id))),
+        Err(SimulationError::RunNotFound(_)) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Ok(Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// Serves a run's event log as JSON Lines, one event per line.
+pub async fn get_simulation_events(
+    State(state): State<Arc<AppState>>,
+    Path(run_id): Path<String>,
+) -> Result<Response, StatusCode> {
+    match state.simulation.events(&run_id) {
+        Ok(Some(events)) => {
+            Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], events).into_response())
+        }
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            log::warn!("Failed to load events for simulation {}: {}", run_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Serves a finished run's report as JSON, or one of its tables as CSV with
+/// `?format=csv&table=...`.
+pub async fn get_simulation_report(
+    State(state): State<Arc<AppState>>,
+    Path(run_id): Path<String>,
+    Query(query): Query<ReportQuery>,
+) -> Result<Response, StatusCode> {
+    let report = match state.simulation.report(&run_id) {
+        Ok(Some(report)) => report,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            log::warn!("Failed to load report for simulation {}: {}", run_id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    match query.format {
+        ReportFormat::Json => Ok(Json(ApiResponse::success(report)).into_response()),
+        ReportFormat::Csv => Ok((
+            [(header::CONTENT_TYPE, "text/csv; charset=utf-8")],
+            report.to_csv(query.table),
+        )
+            .into_response()),
+    }
+}
+
+/// Compares finished runs side by side: their metrics, the differences
+/// from the first run and the config fields that differ.
+pub async fn compare_simulation_runs(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<CompareQuery>,
+) -> Result<Json<ApiResponse<RunComparison>>, StatusCode> {
+    let run_ids: Vec<String> = query
+        .runs
+        .split(',')
+        .map(str::trim)
+        .filter(|run_id| !run_id.is_empty())
+        .map(String::from)
+        .collect();
+    match state.simulation.compare(&run_ids) {
+        Ok(comparison) => Ok(Json(ApiResponse::success(comparison))),
+        Err(SimulationError::RunNotFound(_)) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Ok(Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// Starts a parameter sweep and returns its ID.
+pub async fn start_simulation_sweep(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SweepRequest>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    match state.simulation.sweep(request) {
+        Ok(sweep_id) => Ok(Json(ApiResponse::success(sweep_id))),
+        Err(e) => Ok(Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// Serves a sweep's report as JSON, or its combinations as CSV with
+/// `?format=csv`.
+pub async fn get_simulation_sweep(
+    State(state): State<Arc<AppState>>,
+    Path(sweep_id): Path<String>,
+    Query(query): Query<SweepQuery>,
+) -> Result<Response, StatusCode> {
+    let report = match state.simulation.sweep_report(&sweep_id) {
+        Ok(Some(report)) => report,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            log::warn!("Failed to load simulation sweep {}: {}", sweep_id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    match query.format {
+        ReportFormat::Json => Ok(Json(ApiResponse::success(report)).into_response()),
+        ReportFormat::Csv => Ok((
+            [(header::CONTENT_TYPE, "text/csv; charset=utf-8")],
+            report.to_csv(),
+        )
+            .into_response()),
+    }
+}
+
+pub async fn stop_simulation_sweep(
+    State(state): State<Arc<AppState>>,
+    Path(sweep_id): Path<String>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    command_response(
+        state.simulation.stop_sweep(&sweep_id),
+        "Simulation sweep stopped",
+    )
+}
+
+/// Starts recording the blocks and peer changes seen by the local node to a
+/// new trace, and returns its ID.
+pub async fn start_trace_recording(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    match state.trace_recorder.start(state.network_node.clone()) {
+        Ok(trace_id) => Ok(Json(ApiResponse::success(trace_id))),
+        Err(e) => Ok(Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+pub async fn stop_trace_recording(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    match state.trace_recorder.stop() {
+        Ok(trace_id) => Ok(Json(ApiResponse::success(trace_id))),
+        Err(e) => Ok(Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+pub async fn get_trace_recording(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<TraceStatus>>, StatusCode> {
+    Ok(Json(ApiResponse::success(state.trace_recorder.status())))
+}
+
+pub async fn list_traces(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ApiResponse<Vec<TraceSummary>>>, StatusCode> {
+    match state.trace_recorder.store().summaries() {
+        Ok(traces) => Ok(Json(ApiResponse::success(traces))),
+        Err(e) => {
+            log::warn!("Failed to list traces: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Serves a recorded trace as JSON Lines, one event per line.
+pub async fn get_trace(
+    State(state): State<Arc<AppState>>,
+    Path(trace_id): Path<String>,
+) -> Result<Response, StatusCode> {
+    match state.trace_recorder.store().load_raw(&trace_id) {
+        Ok(Some(trace)) => {
+            Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], trace).into_response())
+        }
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            log::warn!("Failed to load trace {}: {}", trace_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
 }
 
 pub async fn health_check() -> Result<Json<ApiResponse<String>>, StatusCode> {
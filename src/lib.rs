@@ -1,7 +1,15 @@
+pub mod cli;
 pub mod server;
+pub mod simulation;
 pub mod handlers;
 pub mod models;
 pub mod middleware;
+pub mod peers;
+pub mod reputation;
+pub mod stats;
+pub mod topology;
+pub mod trace;
+pub mod block_filter;
 pub mod websocket;
 
 pub use server::*;
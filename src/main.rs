@@ -1,3 +1,4 @@
+use ecoblock_api::cli;
 use ecoblock_api::server::start_api_server;
 use ecoblock_network::NetworkNode;
 use std::sync::Arc;
@@ -6,6 +7,12 @@ use std::sync::Arc;
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize logger
     env_logger::init();
+
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("simulate") {
+        cli::simulate(args).await?;
+        return Ok(());
+    }
     
     println!("🚀 Starting EcoBlock API Server...");
     
@@ -1,8 +1,12 @@
+use axum::extract::State;
 use axum::http::{HeaderValue, Method, Request, StatusCode};
 use axum::response::Response;
+use std::sync::Arc;
 use tower::ServiceBuilder;
 use tower_http::cors::CorsLayer;
 
+use crate::handlers::AppState;
+
 pub fn create_middleware_stack() -> tower::ServiceBuilder<
     tower::layer::util::Stack<tower_http::cors::CorsLayer, tower::layer::util::Identity>,
 > {
@@ -45,3 +49,57 @@ pub async fn logging_middleware(
 
     Ok(response)
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Admin,
+}
+
+/// Bearer tokens accepted by the API and the role each one grants.
+#[derive(Debug, Clone, Default)]
+pub struct AuthConfig {
+    admin_tokens: Vec<String>,
+}
+
+impl AuthConfig {
+    /// Reads admin tokens from the comma-separated `API_ADMIN_TOKENS` variable.
+    /// Without it, admin endpoints reject every request.
+    pub fn from_env() -> Self {
+        let admin_tokens = std::env::var("API_ADMIN_TOKENS")
+            .map(|tokens| {
+                tokens
+                    .split(',')
+                    .map(|token| token.trim().to_string())
+                    .filter(|token| !token.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self { admin_tokens }
+    }
+
+    pub fn role_for(&self, token: &str) -> Option<Role> {
+        self.admin_tokens
+            .iter()
+            .any(|admin| admin == token)
+            .then_some(Role::Admin)
+    }
+}
+
+pub async fn require_admin(
+    State(state): State<Arc<AppState>>,
+    req: Request<axum::body::Body>,
+    next: axum::middleware::Next,
+) -> Result<Response, StatusCode> {
+    let token = req
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match token.map(|token| state.auth.role_for(token)) {
+        Some(Some(Role::Admin)) => Ok(next.run(req).await),
+        Some(None) => Err(StatusCode::FORBIDDEN),
+        None => Err(StatusCode::UNAUTHORIZED),
+    }
+}
@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use ecoblock_network::PeerId;
 use ecoblock_core::SensorData;
 use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiResponse<T> {
@@ -49,6 +50,164 @@ pub struct PeerInfo {
     pub last_seen: u64,
     pub is_connected: bool,
     pub latency: Option<u64>,
+    #[serde(default)]
+    pub reputation: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencySample {
+    pub timestamp: DateTime<Utc>,
+    pub latency: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionEvent {
+    pub timestamp: DateTime<Utc>,
+    pub connected: bool,
+}
+
+/// A peer connecting or disconnecting, as seen by the peer monitor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum PeerEvent {
+    Connected { peer_id: String, address: String },
+    Disconnected { peer_id: String, address: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerError {
+    pub timestamp: DateTime<Utc>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyWindow {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub samples: usize,
+    pub min: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerDetail {
+    pub info: PeerInfo,
+    pub connection_history: Vec<ConnectionEvent>,
+    pub latency_history: Vec<LatencySample>,
+    pub latency_windows: Vec<LatencyWindow>,
+    /// Blocks broadcast while the peer was connected. There is no received
+    /// count: the network node does not say which peer a block came from.
+    pub blocks_sent: u64,
+    pub last_error: Option<PeerError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerDetailQuery {
+    /// Width of each latency percentile window, in seconds, capped at the
+    /// hour of latency history kept per peer.
+    pub window_secs: Option<u64>,
+}
+
+/// Peer behaviour that moves its reputation score. The block events need the
+/// network node to report which peer relayed a block, which it does not do
+/// yet, so only ping results are recorded for now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReputationEvent {
+    /// Relayed a block whose signature does not verify.
+    InvalidSignature,
+    /// Relayed a block the node already had.
+    DuplicateBlock,
+    MissedPing,
+    SuccessfulPing,
+    /// Relayed a block the node did not have yet.
+    UsefulBlock,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReputationChange {
+    pub timestamp: DateTime<Utc>,
+    pub event: ReputationEvent,
+    pub delta: f64,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReputationCounters {
+    pub invalid_signatures: u64,
+    pub duplicate_blocks: u64,
+    pub missed_pings: u64,
+    pub successful_pings: u64,
+    pub useful_blocks: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReputationReport {
+    pub peer_id: String,
+    pub score: f64,
+    pub ban_threshold: f64,
+    pub is_banned: bool,
+    pub counters: ReputationCounters,
+    pub history: Vec<ReputationChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopologyNode {
+    pub id: String,
+    pub address: Option<String>,
+    pub is_local: bool,
+    /// Whether the node's API answered the peer list query.
+    pub reachable: bool,
+    /// Hops from the local node.
+    pub depth: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopologyEdge {
+    pub source: String,
+    pub target: String,
+    pub latency: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NetworkTopology {
+    pub nodes: Vec<TopologyNode>,
+    pub edges: Vec<TopologyEdge>,
+    /// When the last crawl finished; `None` until the first one has.
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectPeerRequest {
+    pub address: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BanPeerRequest {
+    /// Ban length in seconds; a missing value bans the peer permanently.
+    pub duration_secs: Option<u64>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BanEntry {
+    pub peer_id: String,
+    pub address: Option<String>,
+    pub reason: Option<String>,
+    pub banned_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// A change to the ban list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum BanEvent {
+    Banned(BanEntry),
+    Unbanned(BanEntry),
+    Expired(BanEntry),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -86,23 +245,922 @@ pub struct SendBlockRequest {
     pub target_peers: Option<Vec<String>>,
 }
 
+/// Shape of the virtual node graph used by a simulation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Topology {
+    FullMesh,
+    Ring,
+    Star {
+        #[serde(default)]
+        hub: usize,
+    },
+    Line,
+    /// Every pair of nodes is linked with probability `p`.
+    ErdosRenyi { p: f64 },
+    /// Ring lattice of degree `k` with each edge rewired with probability `beta`.
+    WattsStrogatz { k: usize, beta: f64 },
+    /// Preferential attachment, each new node linking to `m` existing nodes.
+    BarabasiAlbert { m: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LatencyModel {
+    Fixed { ms: u64 },
+    Normal { mean_ms: f64, std_dev_ms: f64 },
+}
+
+impl Default for LatencyModel {
+    fn default() -> Self {
+        LatencyModel::Fixed { ms: 10 }
+    }
+}
+
+/// Conditions applied to messages travelling over a simulated link.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LinkModel {
+    #[serde(default)]
+    pub latency: LatencyModel,
+    /// Extra delay drawn uniformly from `0..=jitter_ms`, which also reorders messages.
+    #[serde(default)]
+    pub jitter_ms: u64,
+    /// Probability in `0..=1` that a message is dropped.
+    #[serde(default)]
+    pub loss_rate: f64,
+    /// Link capacity; messages queue behind each other when it is exceeded.
+    #[serde(default)]
+    pub bandwidth_bps: Option<u64>,
+}
+
+/// Link conditions for the link between nodes `a` and `b`, in both directions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkOverride {
+    pub a: usize,
+    pub b: usize,
+    #[serde(flatten)]
+    pub model: LinkModel,
+}
+
+/// Daily temperature cycle peaking at `peak_hour` (UTC).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiurnalCycle {
+    pub mean: f64,
+    pub amplitude: f64,
+    pub peak_hour: f64,
+    pub noise: f64,
+}
+
+/// Value that moves by a normally distributed `step` per sample within `min..=max`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RandomWalk {
+    pub start: f64,
+    pub step: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Noisy baseline with random spikes that decay by `decay` per sample.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PollutionSpikes {
+    pub baseline: f64,
+    pub noise: f64,
+    pub spike_probability: f64,
+    pub spike_magnitude: f64,
+    pub decay: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SensorFault {
+    /// Every reading after the first `after_samples` repeats the last one.
+    StuckAt { after_samples: usize },
+    /// Temperature drifts away from the true value by `per_hour` degrees per hour.
+    Drift { per_hour: f64 },
+}
+
+/// Parameters for generating synthetic `SensorData`. Missing fields take the
+/// values of the `rural` preset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SensorProfile {
+    pub temperature: DiurnalCycle,
+    pub humidity: RandomWalk,
+    pub pm25: PollutionSpikes,
+    pub co2: PollutionSpikes,
+    pub fault: Option<SensorFault>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeSensorProfile {
+    pub nodes: Vec<usize>,
+    #[serde(deserialize_with = "crate::simulation::deserialize_sensor_profile")]
+    pub profile: SensorProfile,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerateSensorDataRequest {
+    #[serde(default, deserialize_with = "crate::simulation::deserialize_sensor_profile")]
+    pub profile: SensorProfile,
+    pub count: usize,
+    /// Time between readings; defaults to one minute.
+    pub interval_ms: Option<u64>,
+    pub seed: Option<u64>,
+    /// Timestamp of the first reading; defaults to `count` intervals ago.
+    pub start: Option<DateTime<Utc>>,
+    /// Also store the readings as blocks on this node.
+    #[serde(default)]
+    pub submit: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartitionRequest {
+    /// Node groups that can only reach nodes in the same group. Nodes not
+    /// listed form one more group together.
+    #[serde(deserialize_with = "crate::simulation::deserialize_node_groups")]
+    pub groups: Vec<Vec<usize>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationGraph {
+    pub node_count: usize,
+    pub edges: Vec<[usize; 2]>,
+    pub degrees: Vec<usize>,
+    pub average_degree: f64,
+    pub connected: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopologyPreviewRequest {
+    #[serde(deserialize_with = "crate::simulation::deserialize_topology")]
+    pub topology: Topology,
+    pub node_count: usize,
+}
+
+/// Misbehaviour of an adversarial virtual node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AdversaryBehaviour {
+    /// Blocks carry signatures that honest nodes reject.
+    InvalidSignatures,
+    /// Every block is created twice with the same parents and different
+    /// data, each version sent to half of the node's neighbours.
+    ConflictingBlocks,
+    /// Every block sent is sent `copies` extra times.
+    DuplicateBlocks { copies: usize },
+    /// Approves the oldest blocks the node knows instead of the newest tips.
+    LazyTips,
+    /// Blocks approve a parent the node never publishes.
+    WithholdParents,
+    /// Creates `blocks` extra blocks every `every_ms` while the run's regular
+    /// blocks are being created.
+    SpamBurst {
+        blocks: usize,
+        #[serde(deserialize_with = "crate::simulation::deserialize_duration_ms")]
+        every_ms: u64,
+    },
+}
+
+/// Share of virtual nodes that misbehave, and how.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdversaryConfig {
+    /// Share of nodes, from 0 to 1, picked at random to be adversarial.
+    pub fraction: f64,
+    pub behaviours: Vec<AdversaryBehaviour>,
+}
+
+/// How fast a run's virtual clock advances relative to wall-clock time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TimeScale {
+    /// Virtual time runs `factor` times as fast as wall-clock time.
+    Factor { factor: f64 },
+    /// Events run back to back without waiting, in discrete-event mode.
+    AsFastAsPossible,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        TimeScale::Factor { factor: 1.0 }
+    }
+}
+
+/// Distribution a duration is drawn from. Durations accept units, e.g. `"30s"`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DurationDistribution {
+    Fixed {
+        #[serde(deserialize_with = "crate::simulation::deserialize_duration_ms")]
+        ms: u64,
+    },
+    Uniform {
+        #[serde(deserialize_with = "crate::simulation::deserialize_duration_ms")]
+        min_ms: u64,
+        #[serde(deserialize_with = "crate::simulation::deserialize_duration_ms")]
+        max_ms: u64,
+    },
+    Exponential {
+        #[serde(deserialize_with = "crate::simulation::deserialize_duration_ms")]
+        mean_ms: u64,
+    },
+    Normal {
+        #[serde(deserialize_with = "crate::simulation::deserialize_duration_ms")]
+        mean_ms: u64,
+        #[serde(deserialize_with = "crate::simulation::deserialize_duration_ms")]
+        std_dev_ms: u64,
+    },
+}
+
+/// Recurring outages that take many nodes offline at once.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutageModel {
+    /// Time from one outage to the next.
+    pub every: DurationDistribution,
+    pub duration: DurationDistribution,
+    /// Share of nodes, from 0 to 1, taken offline by each outage.
+    pub fraction: f64,
+}
+
+/// Nodes leaving and rejoining on their own during a run, e.g. on battery.
+/// Nodes only leave while the run's blocks are being created, so every run
+/// ends with all churned nodes back online.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChurnConfig {
+    /// Time a node stays online before leaving.
+    pub session: DurationDistribution,
+    /// Time a node stays offline before rejoining.
+    pub downtime: DurationDistribution,
+    #[serde(default)]
+    pub outages: Option<OutageModel>,
+}
+
+/// How virtual nodes pick the parents of the blocks they create.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TipSelection {
+    /// The most recent tips, like the local node.
+    #[default]
+    Latest,
+    /// Tips picked at random.
+    Random,
+    /// The parents recorded in the replayed trace.
+    Recorded,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SimulationConfig {
-    pub topology: String,
+    #[serde(deserialize_with = "crate::simulation::deserialize_topology")]
+    pub topology: Topology,
     pub node_count: usize,
     pub block_count: usize,
     pub interval_ms: u64,
+    /// Link conditions applied to every link without an override.
+    #[serde(default)]
+    pub link: LinkModel,
+    #[serde(default)]
+    pub link_overrides: Vec<LinkOverride>,
+    /// Sensor profile used by nodes without a per-node profile.
+    #[serde(default, deserialize_with = "crate::simulation::deserialize_sensor_profile")]
+    pub sensor_profile: SensorProfile,
+    #[serde(default)]
+    pub node_sensor_profiles: Vec<NodeSensorProfile>,
+    /// Seed for every random choice made by a run. Picked at random when
+    /// missing and recorded with the run so it can be replayed.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Virtual wall-clock time at which the run starts, used to timestamp
+    /// sensor readings. Defaults to the real start time.
+    #[serde(default)]
+    pub epoch: Option<DateTime<Utc>>,
+    /// Real time by default; a factor such as `3600` or `"max"` to run
+    /// faster. Results do not depend on it.
+    #[serde(
+        default,
+        deserialize_with = "crate::simulation::deserialize_time_scale"
+    )]
+    pub time_scale: TimeScale,
+    /// Steps applied at fixed virtual times during the run.
+    #[serde(default)]
+    pub timeline: Vec<TimelineStep>,
+    #[serde(default)]
+    pub adversaries: Option<AdversaryConfig>,
+    #[serde(default)]
+    pub churn: Option<ChurnConfig>,
+    #[serde(default)]
+    pub tip_selection: TipSelection,
+    /// Tips approved by each new block; 2 when missing, like the local node.
+    #[serde(default)]
+    pub parent_count: Option<usize>,
+    /// ID of a recorded trace to replay. Its blocks replace the generated
+    /// ones, so `block_count` and `interval_ms` are ignored.
+    #[serde(default)]
+    pub trace: Option<String>,
 }
 
+/// Change applied to a running simulation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SimulationCommand {
+    Partition {
+        /// Each group is a list of nodes; ranges such as `"0-4"` are accepted.
+        #[serde(deserialize_with = "crate::simulation::deserialize_node_groups")]
+        groups: Vec<Vec<usize>>,
+    },
+    Heal,
+    /// Takes nodes offline: they stop creating and relaying blocks and
+    /// messages sent to them are dropped.
+    NodeLeave {
+        #[serde(deserialize_with = "crate::simulation::deserialize_nodes")]
+        nodes: Vec<usize>,
+    },
+    NodeJoin {
+        #[serde(deserialize_with = "crate::simulation::deserialize_nodes")]
+        nodes: Vec<usize>,
+    },
+    /// Multiplies the block creation rate, e.g. `2.0` halves the interval.
+    BlockRate {
+        factor: f64,
+    },
+}
+
+/// Progress event published while a simulation runs.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SimulationUpdateKind {
+    RunStarted {
+        node_count: usize,
+        scenario: Option<String>,
+        replay_of: Option<String>,
+    },
+    BlockCreated {
+        node: usize,
+        block: u64,
+        parents: Vec<u64>,
+    },
+    /// The block has reached every virtual node.
+    PropagationComplete {
+        block: u64,
+        delay_ms: u64,
+    },
+    CommandApplied {
+        command: SimulationCommand,
+    },
+    RunFinished {
+        completed: bool,
+        status: Box<SimulationStatus>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationUpdate {
+    pub run_id: String,
+    /// Virtual time of the event, in milliseconds since the run started.
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub kind: SimulationUpdateKind,
+}
+
+/// A command applied at a fixed point of a run's virtual timeline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimelineStep {
+    /// Virtual time of the step; accepts milliseconds or strings like `"30s"`.
+    #[serde(
+        rename = "at_ms",
+        alias = "at",
+        deserialize_with = "crate::simulation::deserialize_duration_ms"
+    )]
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub command: SimulationCommand,
+}
+
+/// An experiment described as a document: a simulation config plus a
+/// timeline of steps.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(flatten)]
+    pub config: SimulationConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioQuery {
+    /// Only validate the scenario and return it normalised.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SimulationEventKind {
+    BlockCreated {
+        block: u64,
+        origin: usize,
+        parents: Vec<u64>,
+    },
+    BlockDelivered {
+        from: usize,
+        to: usize,
+        block: u64,
+        duplicate: bool,
+        /// Sent in reply to a resync request rather than gossiped.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        resync: bool,
+    },
+    MessageLost {
+        from: usize,
+        to: usize,
+        block: u64,
+    },
+    MessagePartitioned {
+        from: usize,
+        to: usize,
+        block: u64,
+    },
+    MessageOffline {
+        from: usize,
+        to: usize,
+        block: u64,
+    },
+    NodeOffline {
+        node: usize,
+    },
+    NodeOnline {
+        node: usize,
+    },
+    /// A node that came back online asked `from` for the blocks it missed.
+    ResyncRequested {
+        node: usize,
+        from: usize,
+        blocks: usize,
+    },
+    /// An honest node dropped a block with an invalid signature.
+    BlockRejected {
+        from: usize,
+        to: usize,
+        block: u64,
+    },
+    CommandApplied {
+        command: SimulationCommand,
+    },
+}
+
+/// One entry of a run's event log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulationEvent {
+    /// Virtual time of the event, in milliseconds since the run started.
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub kind: SimulationEventKind,
+}
+
+/// A command received by a run, pinned to the point in the event sequence
+/// where it was applied so a replay can apply it at exactly the same point.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedCommand {
+    /// Number of scheduled events processed before the command.
+    pub after_events: u64,
+    pub at_ms: u64,
+    pub command: SimulationCommand,
+}
+
+/// Everything needed to reproduce a run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub run_id: String,
+    /// Config with `seed` and `epoch` filled in.
+    pub config: SimulationConfig,
+    pub replay_of: Option<String>,
+    pub commands: Vec<RecordedCommand>,
+    /// Set when the run was stopped before it completed.
+    pub stopped_after_events: Option<u64>,
+    /// Number of event log entries.
+    pub events: u64,
+    /// SHA-256 of the event log; equal digests mean identical runs.
+    pub event_log_digest: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SimulationStatus {
+    /// ID of the current or most recent run; its report is available once
+    /// the run has finished.
+    pub run_id: Option<String>,
+    /// Run this run is a replay of.
+    pub replay_of: Option<String>,
+    pub seed: Option<u64>,
+    /// Name of the scenario being run, if the run was started from one.
+    pub scenario: Option<String>,
     pub is_running: bool,
+    pub node_count: usize,
+    pub blocks_created: usize,
     pub blocks_sent: usize,
     pub blocks_received: usize,
+    pub duplicate_blocks: usize,
+    pub messages_lost: usize,
+    /// Messages dropped because sender and receiver were partitioned.
+    pub messages_partitioned: usize,
+    pub is_partitioned: bool,
+    /// Messages dropped because the receiving node was offline.
+    pub messages_offline: usize,
+    pub offline_nodes: Vec<usize>,
+    pub adversarial_nodes: Vec<usize>,
+    pub timeline_steps: usize,
+    pub timeline_steps_applied: usize,
+    /// Share of the run's blocks and timeline steps done so far, from 0 to 1.
+    pub progress: f64,
+    /// Most recent sensor reading produced by a virtual node.
+    pub last_reading: Option<SensorData>,
+    /// Position of the simulation clock, in milliseconds since the run started.
+    pub virtual_time_ms: u64,
     pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    /// Wall-clock run time in milliseconds.
     pub duration: Option<u64>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub run_id: String,
+    pub is_running: bool,
+    pub scenario: Option<String>,
+    pub replay_of: Option<String>,
+    pub node_count: usize,
+    pub blocks_created: usize,
+    pub progress: f64,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub samples: usize,
+    pub mean_ms: f64,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    pub min_ms: u64,
+    /// Exclusive upper bound; `None` for the open-ended last bucket.
+    pub max_ms: Option<u64>,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PropagationReport {
+    /// Delay between a block's creation and each other node first receiving it.
+    pub delay: LatencySummary,
+    pub histogram: Vec<HistogramBucket>,
+    /// Delay until a block had reached every node.
+    pub full_propagation: LatencySummary,
+    /// Blocks that never reached every node.
+    pub incomplete_blocks: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmationReport {
+    /// Delay between a block's creation and the first block approving it.
+    pub latency: LatencySummary,
+    pub unconfirmed_blocks: usize,
+    /// Share of blocks that no other block approved by the end of the run.
+    pub orphan_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TipSample {
+    pub virtual_time_ms: u64,
+    /// Blocks not yet approved by any block created so far.
+    pub tips: usize,
+    /// Tips as seen by the nodes' own tangles, averaged over all nodes.
+    pub mean_node_tips: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NodeReport {
+    pub node: usize,
+    #[serde(default)]
+    pub adversarial: bool,
+    pub blocks_created: usize,
+    pub messages_sent: usize,
+    pub messages_received: usize,
+    pub duplicate_blocks: usize,
+}
+
+/// What adversarial nodes did during a run and how the honest nodes fared.
+/// Honest metrics only cover blocks created by, and nodes that are, honest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdversaryReport {
+    pub nodes: Vec<usize>,
+    /// Blocks created by adversarial nodes, including spam and conflicting
+    /// versions.
+    pub blocks_created: usize,
+    pub spam_blocks: usize,
+    /// Second versions of conflicting blocks.
+    pub conflicting_blocks: usize,
+    /// Extra copies of blocks sent.
+    pub duplicate_messages: usize,
+    /// Deliveries of blocks with invalid signatures that honest nodes dropped.
+    pub rejected_blocks: usize,
+    /// Parents approved by adversarial blocks but never published.
+    pub withheld_parents: usize,
+    /// Honest nodes that ended up holding both versions of a conflicting block.
+    pub honest_nodes_with_conflicts: usize,
+    /// Parents honest nodes still had not received at the end of the run,
+    /// summed over honest nodes.
+    pub unresolved_parents: usize,
+    pub honest_propagation: PropagationReport,
+    pub honest_confirmation: ConfirmationReport,
+    /// Mean tip count of honest nodes over the run's tip samples.
+    pub mean_honest_tips: f64,
+}
+
+/// Nodes going offline and catching up on missed blocks when they return.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChurnReport {
+    /// Times a node went offline, for any reason.
+    pub departures: usize,
+    pub rejoins: usize,
+    pub outages: usize,
+    /// Share of node time spent online, from 0 to 1.
+    pub availability: f64,
+    pub resyncs: usize,
+    /// Resyncs where some missed blocks never arrived.
+    pub incomplete_resyncs: usize,
+    /// Blocks nodes asked for after rejoining.
+    pub missed_blocks: usize,
+    pub resynced_blocks: usize,
+    /// Time from rejoining until every missed block had arrived.
+    pub resync_time: LatencySummary,
+}
+
+/// Metrics collected over one simulation run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationReport {
+    pub run_id: String,
+    pub replay_of: Option<String>,
+    pub config: SimulationConfig,
+    /// SHA-256 of the run's event log.
+    pub event_log_digest: String,
+    /// `false` if the run was stopped before every block was delivered.
+    pub completed: bool,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub virtual_duration_ms: u64,
+    pub blocks_created: usize,
+    pub blocks_sent: usize,
+    pub blocks_received: usize,
+    pub duplicate_blocks: usize,
+    pub messages_lost: usize,
+    pub messages_partitioned: usize,
+    pub messages_offline: usize,
+    pub propagation: PropagationReport,
+    pub confirmation: ConfirmationReport,
+    /// Tip counts over virtual time, thinned to at most 1000 evenly spaced
+    /// samples on long runs.
+    pub tip_counts: Vec<TipSample>,
+    pub nodes: Vec<NodeReport>,
+    /// Present when the run had adversarial nodes.
+    #[serde(default)]
+    pub adversaries: Option<AdversaryReport>,
+    /// Present when the run had churn or nodes went offline.
+    #[serde(default)]
+    pub churn: Option<ChurnReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompareQuery {
+    /// Comma-separated run IDs; the first run is the baseline.
+    pub runs: String,
+}
+
+/// One metric across the compared runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricComparison {
+    pub metric: String,
+    /// The metric for each run, in the order the runs were given.
+    pub values: Vec<f64>,
+    /// Difference from the baseline's value.
+    pub differences: Vec<f64>,
+    /// Difference relative to the baseline's value, or `None` where that
+    /// is 0.
+    pub relative_differences: Vec<Option<f64>>,
+}
+
+/// A config field whose value is not the same in every compared run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigDifference {
+    /// Dotted path of the field, e.g. `link.loss_rate`.
+    pub field: String,
+    /// The field's value in each run, `null` where it is missing.
+    pub values: Vec<serde_json::Value>,
+}
+
+/// Finished runs side by side, compared against the first one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunComparison {
+    pub runs: Vec<String>,
+    pub completed: Vec<bool>,
+    pub metrics: Vec<MetricComparison>,
+    pub config_diff: Vec<ConfigDifference>,
+}
+
+/// Values a swept parameter takes: a list such as `[0.0, 0.1]`, or a range
+/// such as `{ "min": 10, "max": 50, "step": 10 }` including both ends.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SweepValues {
+    List(Vec<f64>),
+    Range { min: f64, max: f64, step: f64 },
+}
+
+/// Parameters varied by a sweep; the rest keep the base config's values.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SweepParameters {
+    #[serde(default)]
+    pub node_count: Option<SweepValues>,
+    #[serde(default)]
+    pub interval_ms: Option<SweepValues>,
+    #[serde(default)]
+    pub parent_count: Option<SweepValues>,
+    /// Loss rate of the default link model.
+    #[serde(default)]
+    pub loss_rate: Option<SweepValues>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepRequest {
+    /// Config every combination starts from. Its seed, picked at random when
+    /// missing, is shared by all combinations.
+    pub base: SimulationConfig,
+    pub parameters: SweepParameters,
+    /// Combinations run at once; 1 when missing, running them one after
+    /// another.
+    #[serde(default)]
+    pub parallelism: Option<usize>,
+}
+
+/// One combination of parameter values in a sweep and how its run went.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepCombination {
+    pub node_count: usize,
+    pub interval_ms: u64,
+    pub parent_count: usize,
+    pub loss_rate: f64,
+    pub run_id: Option<String>,
+    /// Why the combination could not be run.
+    pub error: Option<String>,
+    /// Metrics of the finished run, as listed by run comparisons.
+    pub metrics: Option<BTreeMap<String, f64>>,
+}
+
+/// Spread of one metric over a sweep's finished combinations.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepMetricSummary {
+    pub metric: String,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// Index of the combination with the lowest value.
+    pub min_combination: usize,
+    /// Index of the combination with the highest value.
+    pub max_combination: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepReport {
+    pub sweep_id: String,
+    pub base: SimulationConfig,
+    pub parameters: SweepParameters,
+    pub parallelism: usize,
+    pub is_running: bool,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub finished_runs: usize,
+    pub combinations: Vec<SweepCombination>,
+    pub summary: Vec<SweepMetricSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepQuery {
+    #[serde(default)]
+    pub format: ReportFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+/// Section of a report rendered when exporting as CSV.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportTable {
+    #[default]
+    Summary,
+    PropagationHistogram,
+    TipCounts,
+    Nodes,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportQuery {
+    #[serde(default)]
+    pub format: ReportFormat,
+    #[serde(default)]
+    pub table: ReportTable,
+}
+
+/// Something the local node saw while a trace was being recorded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TraceEventKind {
+    Block {
+        id: String,
+        parents: Vec<String>,
+        data: SensorData,
+    },
+    PeerConnected {
+        peer: String,
+        address: String,
+    },
+    PeerDisconnected {
+        peer: String,
+    },
+}
+
+/// One line of a trace file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceEvent {
+    /// Milliseconds since the recording started.
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub kind: TraceEventKind,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TraceStatus {
+    pub recording: bool,
+    /// The trace being recorded, or the last one recorded.
+    pub trace_id: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub stopped_at: Option<DateTime<Utc>>,
+    pub blocks: usize,
+    pub peer_events: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceSummary {
+    pub trace_id: String,
+    pub blocks: usize,
+    pub peer_events: usize,
+    pub duration_ms: u64,
+}
+
+/// Stream of WebSocket messages a client can subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WsChannel {
+    Network,
+    Blocks,
+    Peers,
+    Alerts,
+    Simulation,
+}
+
+/// Message sent by a WebSocket client. `id` is optional and echoed in the
+/// reply so clients can match replies to requests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsClientMessage {
+    Subscribe {
+        #[serde(default)]
+        id: Option<String>,
+        channels: Vec<WsChannel>,
+        /// Conditions such as `temperature > 30` that blocks must all meet to
+        /// be sent on the `blocks` channel. Replaces any earlier filters.
+        #[serde(default)]
+        filters: Vec<String>,
+        /// Sequence number of the last event received before reconnecting;
+        /// buffered events after it are replayed.
+        #[serde(default)]
+        last_seq: Option<u64>,
+    },
+    Unsubscribe {
+        #[serde(default)]
+        id: Option<String>,
+        channels: Vec<WsChannel>,
+    },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Block {
     pub hash: String,
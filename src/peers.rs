@@ -0,0 +1,510 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use futures::future::join_all;
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::{
+    net::TcpStream,
+    sync::broadcast,
+    time::{interval, timeout},
+};
+
+use crate::models::{
+    BanEntry, BanEvent, ConnectionEvent, LatencySample, LatencyWindow, PeerError, PeerEvent,
+    ReputationEvent,
+};
+use crate::reputation::ReputationTracker;
+use crate::stats::percentile;
+use ecoblock_network::NetworkNode;
+
+const DEFAULT_BAN_LIST_PATH: &str = "data/ban_list.json";
+const BAN_ENFORCEMENT_INTERVAL: Duration = Duration::from_secs(10);
+const PEER_SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+/// How long a probe of a peer without a latency measurement may take.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+const LATENCY_HISTORY_CAPACITY: usize = 360;
+/// Time a full latency history covers; wider windows are capped to it.
+const LATENCY_HISTORY_SPAN: Duration =
+    Duration::from_secs(PEER_SAMPLE_INTERVAL.as_secs() * LATENCY_HISTORY_CAPACITY as u64);
+const CONNECTION_HISTORY_CAPACITY: usize = 100;
+/// Peer and ban events buffered per subscriber before slow ones miss events.
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Persisted list of banned peers, keyed by peer id.
+///
+/// The list is written back to disk on every change so bans survive restarts.
+#[derive(Clone)]
+pub struct BanList {
+    path: PathBuf,
+    entries: Arc<RwLock<HashMap<String, BanEntry>>>,
+    events: broadcast::Sender<BanEvent>,
+}
+
+impl BanList {
+    pub fn from_env() -> Self {
+        let path = std::env::var("BAN_LIST_PATH").unwrap_or_else(|_| DEFAULT_BAN_LIST_PATH.into());
+        Self::load(path)
+    }
+
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let entries = match fs::read_to_string(&path) {
+            Ok(contents) => match serde_json::from_str::<Vec<BanEntry>>(&contents) {
+                Ok(entries) => entries
+                    .into_iter()
+                    .map(|entry| (entry.peer_id.clone(), entry))
+                    .collect(),
+                Err(e) => {
+                    log::warn!("Ignoring unreadable ban list {}: {}", path.display(), e);
+                    HashMap::new()
+                }
+            },
+            Err(_) => HashMap::new(),
+        };
+
+        Self {
+            path,
+            entries: Arc::new(RwLock::new(entries)),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    /// Bans, unbans and expiries from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<BanEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn ban(
+        &self,
+        peer_id: String,
+        address: Option<String>,
+        duration: Option<Duration>,
+        reason: Option<String>,
+    ) -> anyhow::Result<BanEntry> {
+        let banned_at = Utc::now();
+        let expires_at = match duration {
+            Some(duration) => Some(banned_at + ChronoDuration::from_std(duration)?),
+            None => None,
+        };
+        let entry = BanEntry {
+            peer_id: peer_id.clone(),
+            address,
+            reason,
+            banned_at,
+            expires_at,
+        };
+
+        let mut entries = self.entries.write().unwrap();
+        entries.insert(peer_id, entry.clone());
+        self.persist(&entries)?;
+        // No receivers just means nothing is listening.
+        let _ = self.events.send(BanEvent::Banned(entry.clone()));
+        Ok(entry)
+    }
+
+    pub fn unban(&self, peer_id: &str) -> anyhow::Result<Option<BanEntry>> {
+        let mut entries = self.entries.write().unwrap();
+        let removed = entries.remove(peer_id);
+        if let Some(entry) = &removed {
+            self.persist(&entries)?;
+            let _ = self.events.send(BanEvent::Unbanned(entry.clone()));
+        }
+        Ok(removed)
+    }
+
+    pub fn is_banned(&self, peer_id: &str) -> bool {
+        let now = Utc::now();
+        self.entries
+            .read()
+            .unwrap()
+            .get(peer_id)
+            .is_some_and(|entry| entry.is_active(now))
+    }
+
+    pub fn is_address_banned(&self, address: &str) -> bool {
+        let now = Utc::now();
+        self.entries
+            .read()
+            .unwrap()
+            .values()
+            .any(|entry| entry.address.as_deref() == Some(address) && entry.is_active(now))
+    }
+
+    /// Returns the bans that are still in effect.
+    pub fn active(&self) -> Vec<BanEntry> {
+        let now = Utc::now();
+        self.entries
+            .read()
+            .unwrap()
+            .values()
+            .filter(|entry| entry.is_active(now))
+            .cloned()
+            .collect()
+    }
+
+    /// Drops expired bans and returns them.
+    pub fn prune_expired(&self) -> anyhow::Result<Vec<BanEntry>> {
+        let now = Utc::now();
+        let mut entries = self.entries.write().unwrap();
+        let expired: Vec<String> = entries
+            .values()
+            .filter(|entry| !entry.is_active(now))
+            .map(|entry| entry.peer_id.clone())
+            .collect();
+
+        if expired.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let removed: Vec<BanEntry> = expired
+            .iter()
+            .filter_map(|peer_id| entries.remove(peer_id))
+            .collect();
+        self.persist(&entries)?;
+        for entry in &removed {
+            let _ = self.events.send(BanEvent::Expired(entry.clone()));
+        }
+        Ok(removed)
+    }
+
+    fn persist(&self, entries: &HashMap<String, BanEntry>) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let entries: Vec<&BanEntry> = entries.values().collect();
+        fs::write(&self.path, serde_json::to_vec_pretty(&entries)?)?;
+        Ok(())
+    }
+}
+
+impl BanEntry {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        !matches!(self.expires_at, Some(expires_at) if expires_at <= now)
+    }
+}
+
+/// Periodically drops expired bans and disconnects any banned peer the
+/// network node is still talking to.
+pub fn spawn_ban_enforcer(network_node: Arc<NetworkNode>, ban_list: BanList) {
+    tokio::spawn(async move {
+        let mut ticker = interval(BAN_ENFORCEMENT_INTERVAL);
+        loop {
+            ticker.tick().await;
+
+            if let Err(e) = ban_list.prune_expired() {
+                log::warn!("Failed to prune expired bans: {}", e);
+            }
+
+            let peers = network_node.peer_discovery.get_peers().await;
+            for (id, info) in peers.iter().filter(|(_, info)| info.is_connected) {
+                let banned = ban_list.is_banned(&id.0.to_string())
+                    || ban_list.is_address_banned(&info.address.to_string());
+                if !banned {
+                    continue;
+                }
+                if let Err(e) = network_node.disconnect_peer(id).await {
+                    log::warn!("Failed to disconnect banned peer {}: {}", id.0, e);
+                }
+            }
+        }
+    });
+}
+
+/// Everything the monitor has observed about a single peer.
+#[derive(Debug, Clone)]
+pub struct PeerHistory {
+    pub address: String,
+    pub is_connected: bool,
+    pub latency_samples: VecDeque<LatencySample>,
+    pub connection_events: VecDeque<ConnectionEvent>,
+    pub blocks_sent: u64,
+    pub last_error: Option<PeerError>,
+}
+
+impl PeerHistory {
+    fn new(address: String) -> Self {
+        Self {
+            address,
+            is_connected: false,
+            latency_samples: VecDeque::with_capacity(LATENCY_HISTORY_CAPACITY),
+            connection_events: VecDeque::new(),
+            blocks_sent: 0,
+            last_error: None,
+        }
+    }
+
+    fn push_sample(&mut self, sample: LatencySample) {
+        if self.latency_samples.len() == LATENCY_HISTORY_CAPACITY {
+            self.latency_samples.pop_front();
+        }
+        self.latency_samples.push_back(sample);
+    }
+
+    fn set_connected(&mut self, connected: bool, timestamp: DateTime<Utc>) {
+        if self.is_connected == connected && !self.connection_events.is_empty() {
+            return;
+        }
+        self.is_connected = connected;
+        if self.connection_events.len() == CONNECTION_HISTORY_CAPACITY {
+            self.connection_events.pop_front();
+        }
+        self.connection_events.push_back(ConnectionEvent {
+            timestamp,
+            connected,
+        });
+    }
+
+    /// Splits the latency history into consecutive windows of `window` length
+    /// and computes percentiles for each window that holds at least one sample.
+    /// The window is kept between one second and the span of the history.
+    pub fn latency_windows(&self, window: Duration) -> Vec<LatencyWindow> {
+        let window = window.clamp(Duration::from_secs(1), LATENCY_HISTORY_SPAN);
+        let window =
+            ChronoDuration::from_std(window).unwrap_or_else(|_| ChronoDuration::minutes(5));
+        let mut windows = Vec::new();
+        let mut samples = self
+            .latency_samples
+            .iter()
+            .filter_map(|sample| sample.latency.map(|latency| (sample.timestamp, latency)))
+            .peekable();
+
+        while let Some(&(start, _)) = samples.peek() {
+            let end = start + window;
+            let mut latencies = Vec::new();
+            while let Some(&(timestamp, latency)) = samples.peek() {
+                if timestamp >= end {
+                    break;
+                }
+                latencies.push(latency);
+                samples.next();
+            }
+            latencies.sort_unstable();
+            windows.push(LatencyWindow {
+                start,
+                end,
+                samples: latencies.len(),
+                min: latencies[0],
+                p50: percentile(&latencies, 50.0),
+                p90: percentile(&latencies, 90.0),
+                p99: percentile(&latencies, 99.0),
+                max: latencies[latencies.len() - 1],
+            });
+        }
+
+        windows
+    }
+}
+
+/// Keeps a bounded history of latency samples, connection changes and
+/// exchanged blocks for every peer the node has seen.
+#[derive(Clone)]
+pub struct PeerMonitor {
+    peers: Arc<RwLock<HashMap<String, PeerHistory>>>,
+    events: broadcast::Sender<PeerEvent>,
+}
+
+impl Default for PeerMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PeerMonitor {
+    pub fn new() -> Self {
+        Self {
+            peers: Arc::new(RwLock::new(HashMap::new())),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    /// Peers connecting and disconnecting from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<PeerEvent> {
+        self.events.subscribe()
+    }
+
+    /// Publishes a change in `history`'s connection state.
+    fn announce(&self, peer_id: &str, history: &PeerHistory) {
+        let peer_id = peer_id.to_string();
+        let address = history.address.clone();
+        let event = if history.is_connected {
+            PeerEvent::Connected { peer_id, address }
+        } else {
+            PeerEvent::Disconnected { peer_id, address }
+        };
+        // No receivers just means nothing is listening.
+        let _ = self.events.send(event);
+    }
+
+    pub fn history(&self, peer_id: &str) -> Option<PeerHistory> {
+        self.peers.read().unwrap().get(peer_id).cloned()
+    }
+
+    pub fn record_sample(
+        &self,
+        peer_id: &str,
+        address: String,
+        connected: bool,
+        latency: Option<u64>,
+    ) {
+        let timestamp = Utc::now();
+        let mut peers = self.peers.write().unwrap();
+        let history = peers
+            .entry(peer_id.to_string())
+            .or_insert_with(|| PeerHistory::new(address.clone()));
+
+        history.address = address;
+        let was_connected = history.is_connected;
+        if was_connected && !connected {
+            history.last_error = Some(PeerError {
+                timestamp,
+                message: "Connection lost".to_string(),
+            });
+        }
+        history.set_connected(connected, timestamp);
+        history.push_sample(LatencySample { timestamp, latency });
+        if was_connected != connected {
+            self.announce(peer_id, history);
+        }
+    }
+
+    /// Marks peers that disappeared from discovery as disconnected.
+    pub fn record_missing(&self, seen: &[String]) {
+        let timestamp = Utc::now();
+        let mut peers = self.peers.write().unwrap();
+        for (peer_id, history) in peers.iter_mut() {
+            if history.is_connected && !seen.contains(peer_id) {
+                history.set_connected(false, timestamp);
+                self.announce(peer_id, history);
+            }
+        }
+    }
+
+    pub fn record_block_sent(&self, peer_id: &str) {
+        if let Some(history) = self.peers.write().unwrap().get_mut(peer_id) {
+            history.blocks_sent += 1;
+        }
+    }
+
+    pub fn record_error(&self, peer_id: &str, message: impl Into<String>) {
+        if let Some(history) = self.peers.write().unwrap().get_mut(peer_id) {
+            history.last_error = Some(PeerError {
+                timestamp: Utc::now(),
+                message: message.into(),
+            });
+        }
+    }
+}
+
+/// Whether a TCP connection to the peer's address opens in time.
+async fn probe(address: SocketAddr) -> bool {
+    matches!(
+        timeout(PROBE_TIMEOUT, TcpStream::connect(address)).await,
+        Ok(Ok(_))
+    )
+}
+
+/// Samples every known peer on a fixed interval into the monitor's history,
+/// scoring connected peers on whether they answered the last ping. Peers the
+/// node has no latency for yet are probed instead, so only a probe that fails
+/// counts as a missed ping.
+pub fn spawn_peer_sampler(
+    network_node: Arc<NetworkNode>,
+    monitor: PeerMonitor,
+    reputation: ReputationTracker,
+) {
+    tokio::spawn(async move {
+        let mut ticker = interval(PEER_SAMPLE_INTERVAL);
+        loop {
+            ticker.tick().await;
+
+            let peers = network_node.peer_discovery.get_peers().await;
+            let seen: Vec<String> = peers.keys().map(|id| id.0.to_string()).collect();
+            let mut probes = Vec::new();
+            for (id, info) in &peers {
+                let peer_id = id.0.to_string();
+                monitor.record_sample(
+                    &peer_id,
+                    info.address.to_string(),
+                    info.is_connected,
+                    info.latency,
+                );
+
+                if !info.is_connected {
+                    continue;
+                }
+                let address = info.address;
+                if info.latency.is_some() {
+                    reputation.record(
+                        &peer_id,
+                        Some(address.to_string()),
+                        ReputationEvent::SuccessfulPing,
+                    );
+                } else {
+                    probes.push(async move { (peer_id, address, probe(address).await) });
+                }
+            }
+            monitor.record_missing(&seen);
+
+            for (peer_id, address, answered) in join_all(probes).await {
+                let event = if answered {
+                    ReputationEvent::SuccessfulPing
+                } else {
+                    ReputationEvent::MissedPing
+                };
+                reputation.record(&peer_id, Some(address.to_string()), event);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(latencies: &[(i64, Option<u64>)]) -> PeerHistory {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut history = PeerHistory::new("127.0.0.1:9001".to_string());
+        for &(offset_secs, latency) in latencies {
+            history.push_sample(LatencySample {
+                timestamp: start + ChronoDuration::seconds(offset_secs),
+                latency,
+            });
+        }
+        history
+    }
+
+    #[test]
+    fn latency_windows_split_samples_by_window() {
+        let history = history(&[(0, Some(30)), (10, Some(10)), (20, None), (60, Some(50))]);
+        let windows = history.latency_windows(Duration::from_secs(30));
+
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].samples, 2);
+        assert_eq!((windows[0].min, windows[0].max), (10, 30));
+        assert_eq!(windows[1].samples, 1);
+        assert_eq!(windows[1].p50, 50);
+    }
+
+    #[test]
+    fn latency_windows_cap_huge_windows_at_the_history_span() {
+        let history = history(&[(0, Some(10)), (7200, Some(20))]);
+        let windows = history.latency_windows(Duration::from_secs(9_000_000_000_000_000));
+
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].end - windows[0].start, ChronoDuration::hours(1));
+    }
+
+    #[test]
+    fn latency_windows_treat_a_zero_window_as_one_second() {
+        let history = history(&[(0, Some(10)), (0, Some(20))]);
+        let windows = history.latency_windows(Duration::ZERO);
+
+        assert_eq!(windows.len(), 1);
+        assert_eq!(windows[0].samples, 2);
+    }
+}
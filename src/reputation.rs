@@ -0,0 +1,168 @@
+use chrono::Utc;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use crate::models::{ReputationChange, ReputationCounters, ReputationEvent, ReputationReport};
+use crate::peers::BanList;
+
+const INITIAL_SCORE: f64 = 100.0;
+const MAX_SCORE: f64 = 100.0;
+const DEFAULT_BAN_THRESHOLD: f64 = 20.0;
+const DEFAULT_BAN_DURATION: Duration = Duration::from_secs(3600);
+const REPUTATION_HISTORY_CAPACITY: usize = 200;
+
+impl ReputationEvent {
+    /// Score change applied when the event is recorded.
+    pub fn weight(self) -> f64 {
+        match self {
+            ReputationEvent::InvalidSignature => -25.0,
+            ReputationEvent::DuplicateBlock => -2.0,
+            ReputationEvent::MissedPing => -5.0,
+            ReputationEvent::SuccessfulPing => 0.5,
+            ReputationEvent::UsefulBlock => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReputationConfig {
+    /// Peers whose score drops below this value are banned temporarily.
+    pub ban_threshold: f64,
+    pub ban_duration: Duration,
+}
+
+impl ReputationConfig {
+    /// Reads `REPUTATION_BAN_THRESHOLD` and `REPUTATION_BAN_SECS`.
+    pub fn from_env() -> Self {
+        let ban_threshold = std::env::var("REPUTATION_BAN_THRESHOLD")
+            .ok()
+            .and_then(|threshold| threshold.parse().ok())
+            .unwrap_or(DEFAULT_BAN_THRESHOLD);
+        let ban_duration = std::env::var("REPUTATION_BAN_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_BAN_DURATION);
+
+        Self {
+            ban_threshold,
+            ban_duration,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PeerReputation {
+    score: f64,
+    counters: ReputationCounters,
+    history: VecDeque<ReputationChange>,
+}
+
+impl PeerReputation {
+    fn new() -> Self {
+        Self {
+            score: INITIAL_SCORE,
+            counters: ReputationCounters::default(),
+            history: VecDeque::new(),
+        }
+    }
+
+    fn push_change(&mut self, change: ReputationChange) {
+        if self.history.len() == REPUTATION_HISTORY_CAPACITY {
+            self.history.pop_front();
+        }
+        self.history.push_back(change);
+    }
+}
+
+/// Scores peers from their observed behaviour and bans those that fall below
+/// the configured threshold.
+#[derive(Clone)]
+pub struct ReputationTracker {
+    config: ReputationConfig,
+    ban_list: BanList,
+    peers: Arc<RwLock<HashMap<String, PeerReputation>>>,
+}
+
+impl ReputationTracker {
+    pub fn new(config: ReputationConfig, ban_list: BanList) -> Self {
+        Self {
+            config,
+            ban_list,
+            peers: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn score(&self, peer_id: &str) -> Option<f64> {
+        self.peers
+            .read()
+            .unwrap()
+            .get(peer_id)
+            .map(|reputation| reputation.score)
+    }
+
+    pub fn report(&self, peer_id: &str) -> Option<ReputationReport> {
+        let peers = self.peers.read().unwrap();
+        let reputation = peers.get(peer_id)?;
+        Some(ReputationReport {
+            peer_id: peer_id.to_string(),
+            score: reputation.score,
+            ban_threshold: self.config.ban_threshold,
+            is_banned: self.ban_list.is_banned(peer_id),
+            counters: reputation.counters.clone(),
+            history: reputation.history.iter().cloned().collect(),
+        })
+    }
+
+    /// Applies `event` to the peer's score, banning the peer if the score
+    /// drops below the threshold. Returns the new score.
+    pub fn record(&self, peer_id: &str, address: Option<String>, event: ReputationEvent) -> f64 {
+        let mut peers = self.peers.write().unwrap();
+        let reputation = peers
+            .entry(peer_id.to_string())
+            .or_insert_with(PeerReputation::new);
+
+        match event {
+            ReputationEvent::InvalidSignature => reputation.counters.invalid_signatures += 1,
+            ReputationEvent::DuplicateBlock => reputation.counters.duplicate_blocks += 1,
+            ReputationEvent::MissedPing => reputation.counters.missed_pings += 1,
+            ReputationEvent::SuccessfulPing => reputation.counters.successful_pings += 1,
+            ReputationEvent::UsefulBlock => reputation.counters.useful_blocks += 1,
+        }
+
+        let delta = event.weight();
+        reputation.score = (reputation.score + delta).clamp(0.0, MAX_SCORE);
+        reputation.push_change(ReputationChange {
+            timestamp: Utc::now(),
+            event,
+            delta,
+            score: reputation.score,
+        });
+
+        if reputation.score < self.config.ban_threshold && !self.ban_list.is_banned(peer_id) {
+            let reason = format!(
+                "Reputation score {:.1} fell below {:.1}",
+                reputation.score, self.config.ban_threshold
+            );
+            match self.ban_list.ban(
+                peer_id.to_string(),
+                address,
+                Some(self.config.ban_duration),
+                Some(reason),
+            ) {
+                Ok(_) => {
+                    log::info!("Temporarily banned peer {} for low reputation", peer_id);
+                    // Once the ban expires the peer comes back at the threshold,
+                    // so any further misbehaviour bans it again.
+                    reputation.score = self.config.ban_threshold;
+                }
+                Err(e) => log::warn!("Failed to ban peer {}: {}", peer_id, e),
+            }
+        }
+
+        reputation.score
+    }
+}
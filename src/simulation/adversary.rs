@@ -0,0 +1,116 @@
+use rand::{seq::index::sample, Rng};
+
+use super::SimulationError;
+use crate::models::{AdversaryBehaviour, AdversaryConfig};
+
+impl AdversaryConfig {
+    pub fn validate(&self) -> Result<(), SimulationError> {
+        if !(0.0..=1.0).contains(&self.fraction) {
+            return Err(SimulationError::InvalidConfig(
+                "adversaries fraction must be between 0 and 1".to_string(),
+            ));
+        }
+        for behaviour in &self.behaviours {
+            match behaviour {
+                AdversaryBehaviour::DuplicateBlocks { copies: 0 } => {
+                    return Err(SimulationError::InvalidConfig(
+                        "duplicate_blocks copies must be at least 1".to_string(),
+                    ))
+                }
+                AdversaryBehaviour::SpamBurst { blocks, every_ms }
+                    if *blocks == 0 || *every_ms == 0 =>
+                {
+                    return Err(SimulationError::InvalidConfig(
+                        "spam_burst blocks and every_ms must be greater than 0".to_string(),
+                    ))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What adversarial nodes have done so far in a run.
+#[derive(Debug, Default)]
+pub(crate) struct AdversaryTally {
+    pub spam_blocks: usize,
+    pub conflicting_blocks: usize,
+    pub duplicate_messages: usize,
+    pub rejected_blocks: usize,
+    pub withheld_parents: usize,
+    /// Filled in when the run ends.
+    pub honest_nodes_with_conflicts: usize,
+    /// Filled in when the run ends.
+    pub unresolved_parents: usize,
+}
+
+/// The adversarial nodes of a run. Every adversarial node shows all of the
+/// configured behaviours.
+pub(crate) struct Adversaries {
+    adversarial: Vec<bool>,
+    pub invalid_signatures: bool,
+    pub conflicting_blocks: bool,
+    /// Extra copies of each block sent.
+    pub duplicate_copies: usize,
+    pub lazy_tips: bool,
+    pub withhold_parents: bool,
+    /// Blocks per burst and time between bursts.
+    pub spam: Option<(usize, u64)>,
+    pub tally: AdversaryTally,
+}
+
+impl Adversaries {
+    /// Picks the configured share of nodes at random. Without a config no
+    /// node is adversarial and `rng` is left untouched.
+    pub fn new<R: Rng>(config: Option<&AdversaryConfig>, node_count: usize, rng: &mut R) -> Self {
+        let mut adversaries = Self {
+            adversarial: vec![false; node_count],
+            invalid_signatures: false,
+            conflicting_blocks: false,
+            duplicate_copies: 0,
+            lazy_tips: false,
+            withhold_parents: false,
+            spam: None,
+            tally: AdversaryTally::default(),
+        };
+        let Some(config) = config else {
+            return adversaries;
+        };
+
+        let count = (config.fraction * node_count as f64).round() as usize;
+        for node in sample(rng, node_count, count.min(node_count)) {
+            adversaries.adversarial[node] = true;
+        }
+        for behaviour in &config.behaviours {
+            match *behaviour {
+                AdversaryBehaviour::InvalidSignatures => adversaries.invalid_signatures = true,
+                AdversaryBehaviour::ConflictingBlocks => adversaries.conflicting_blocks = true,
+                AdversaryBehaviour::DuplicateBlocks { copies } => {
+                    adversaries.duplicate_copies = copies
+                }
+                AdversaryBehaviour::LazyTips => adversaries.lazy_tips = true,
+                AdversaryBehaviour::WithholdParents => adversaries.withhold_parents = true,
+                AdversaryBehaviour::SpamBurst { blocks, every_ms } => {
+                    adversaries.spam = Some((blocks, every_ms))
+                }
+            }
+        }
+        adversaries
+    }
+
+    pub fn is_adversarial(&self, node: usize) -> bool {
+        self.adversarial[node]
+    }
+
+    /// Per-node flags, indexed by node.
+    pub fn flags(&self) -> &[bool] {
+        &self.adversarial
+    }
+
+    pub fn nodes(&self) -> Vec<usize> {
+        (0..self.adversarial.len())
+            .filter(|&node| self.adversarial[node])
+            .collect()
+    }
+}
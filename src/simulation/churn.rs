@@ -0,0 +1,182 @@
+use rand::Rng;
+
+use super::report::summarize;
+use super::{standard_normal, SimulationError};
+use crate::models::{ChurnConfig, ChurnReport, DurationDistribution};
+
+impl DurationDistribution {
+    pub fn validate(&self) -> Result<(), SimulationError> {
+        if let DurationDistribution::Uniform { min_ms, max_ms } = self {
+            if min_ms > max_ms {
+                return Err(SimulationError::InvalidConfig(
+                    "uniform min_ms must not exceed max_ms".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws a duration of at least 1 ms, so a node never leaves and rejoins
+    /// at the same instant.
+    pub(crate) fn sample<R: Rng>(&self, rng: &mut R) -> u64 {
+        let ms = match *self {
+            DurationDistribution::Fixed { ms } => ms,
+            DurationDistribution::Uniform { min_ms, max_ms } => rng.gen_range(min_ms..=max_ms),
+            DurationDistribution::Exponential { mean_ms } => {
+                let u: f64 = rng.gen_range(f64::EPSILON..1.0);
+                (-(mean_ms as f64) * u.ln()).round() as u64
+            }
+            DurationDistribution::Normal {
+                mean_ms,
+                std_dev_ms,
+            } => (mean_ms as f64 + std_dev_ms as f64 * standard_normal(rng))
+                .max(0.0)
+                .round() as u64,
+        };
+        ms.max(1)
+    }
+}
+
+impl ChurnConfig {
+    pub fn validate(&self) -> Result<(), SimulationError> {
+        self.session.validate()?;
+        self.downtime.validate()?;
+        if let Some(outages) = &self.outages {
+            outages.every.validate()?;
+            outages.duration.validate()?;
+            if !(0.0..=1.0).contains(&outages.fraction) {
+                return Err(SimulationError::InvalidConfig(
+                    "outage fraction must be between 0 and 1".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Why a node is offline; it is online when none of these apply.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Downtime {
+    /// Taken offline by a `node_leave` command.
+    pub left: bool,
+    /// Between sessions under the churn model.
+    pub churned: bool,
+    /// Number of outages the node is caught in.
+    pub outages: u32,
+    /// Disconnected from the recording node in a replayed trace.
+    pub disconnected: bool,
+}
+
+impl Downtime {
+    pub fn is_online(&self) -> bool {
+        !self.left && !self.churned && self.outages == 0 && !self.disconnected
+    }
+}
+
+/// A rejoined node catching up on the blocks it missed.
+struct Resync {
+    started_ms: u64,
+    blocks: usize,
+    pending: usize,
+    finished_ms: Option<u64>,
+}
+
+/// Tracks nodes going offline and coming back over a run, and their resyncs.
+pub(crate) struct ChurnTracker {
+    offline_since: Vec<Option<u64>>,
+    offline_ms: Vec<u64>,
+    departures: usize,
+    rejoins: usize,
+    pub outages: usize,
+    resyncs: Vec<Resync>,
+}
+
+impl ChurnTracker {
+    pub fn new(node_count: usize) -> Self {
+        Self {
+            offline_since: vec![None; node_count],
+            offline_ms: vec![0; node_count],
+            departures: 0,
+            rejoins: 0,
+            outages: 0,
+            resyncs: Vec::new(),
+        }
+    }
+
+    pub fn went_offline(&mut self, node: usize, now_ms: u64) {
+        self.offline_since[node] = Some(now_ms);
+        self.departures += 1;
+    }
+
+    pub fn came_online(&mut self, node: usize, now_ms: u64) {
+        if let Some(since) = self.offline_since[node].take() {
+            self.offline_ms[node] = self.offline_ms[node].saturating_add(now_ms - since);
+        }
+        self.rejoins += 1;
+    }
+
+    /// Starts a resync of `blocks` missed blocks and returns its ID.
+    pub fn resync_started(&mut self, blocks: usize, now_ms: u64) -> usize {
+        self.resyncs.push(Resync {
+            started_ms: now_ms,
+            blocks,
+            pending: blocks,
+            finished_ms: (blocks == 0).then_some(now_ms),
+        });
+        self.resyncs.len() - 1
+    }
+
+    /// A block requested by resync `id` arrived.
+    pub fn resync_block(&mut self, id: usize, now_ms: u64) {
+        let resync = &mut self.resyncs[id];
+        resync.pending -= 1;
+        if resync.pending == 0 {
+            resync.finished_ms = Some(now_ms);
+        }
+    }
+
+    /// Builds the report, or `None` if no node ever went offline and the run
+    /// had no churn configured.
+    pub fn report(&self, configured: bool, end_ms: u64) -> Option<ChurnReport> {
+        if !configured && self.departures == 0 {
+            return None;
+        }
+        // Summed as floats: virtual times can be close to `u64::MAX`.
+        let node_time = end_ms as f64 * self.offline_ms.len() as f64;
+        let offline_time: f64 = self
+            .offline_ms
+            .iter()
+            .zip(&self.offline_since)
+            .map(|(&offline, since)| {
+                offline as f64 + since.map_or(0.0, |since| (end_ms - since) as f64)
+            })
+            .sum();
+        let missed_blocks = self.resyncs.iter().map(|resync| resync.blocks).sum();
+        let pending: usize = self.resyncs.iter().map(|resync| resync.pending).sum();
+
+        Some(ChurnReport {
+            departures: self.departures,
+            rejoins: self.rejoins,
+            outages: self.outages,
+            availability: if node_time == 0.0 {
+                1.0
+            } else {
+                1.0 - offline_time / node_time
+            },
+            resyncs: self.resyncs.len(),
+            incomplete_resyncs: self
+                .resyncs
+                .iter()
+                .filter(|resync| resync.finished_ms.is_none())
+                .count(),
+            missed_blocks,
+            resynced_blocks: missed_blocks - pending,
+            resync_time: summarize(
+                self.resyncs
+                    .iter()
+                    .filter_map(|resync| Some(resync.finished_ms? - resync.started_ms))
+                    .collect(),
+            ),
+        })
+    }
+}
@@ -0,0 +1,73 @@
+use serde::{de::Error as _, Deserialize, Deserializer};
+use std::time::Duration;
+use tokio::time::Instant;
+
+use super::SimulationError;
+use crate::models::TimeScale;
+
+/// Wait used for events too far off to represent as an instant, as tokio
+/// does for its own far-future deadlines.
+const FAR_FUTURE: Duration = Duration::from_secs(86_400 * 365 * 30);
+
+impl TimeScale {
+    pub fn validate(&self) -> Result<(), SimulationError> {
+        match self {
+            TimeScale::Factor { factor } if !(factor.is_finite() && *factor > 0.0) => Err(
+                SimulationError::InvalidConfig("time_scale must be a positive number".to_string()),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Wall-clock instant at which virtual time `at_ms` falls due for a run
+    /// started at `started`.
+    pub(crate) fn due(&self, started: Instant, at_ms: u64) -> Instant {
+        match self {
+            TimeScale::Factor { factor } => {
+                Duration::try_from_secs_f64(at_ms as f64 / 1000.0 / factor)
+                    .ok()
+                    .and_then(|wait| started.checked_add(wait))
+                    .unwrap_or_else(|| Instant::now() + FAR_FUTURE)
+            }
+            TimeScale::AsFastAsPossible => Instant::now(),
+        }
+    }
+
+    /// Virtual time reached by the wall clock, or `now_ms` when the run is
+    /// not paced to wall-clock time.
+    pub(crate) fn virtual_elapsed(&self, started: Instant, now_ms: u64) -> u64 {
+        match self {
+            TimeScale::Factor { factor } => {
+                (started.elapsed().as_secs_f64() * 1000.0 * factor) as u64
+            }
+            TimeScale::AsFastAsPossible => now_ms,
+        }
+    }
+}
+
+/// Accepts a speed-up factor such as `60`, `"max"` for as fast as possible,
+/// or a tagged time scale object.
+pub fn deserialize_time_scale<'de, D>(deserializer: D) -> Result<TimeScale, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum TimeScaleInput {
+        Factor(f64),
+        Name(String),
+        Spec(TimeScale),
+    }
+
+    match TimeScaleInput::deserialize(deserializer)? {
+        TimeScaleInput::Factor(factor) => Ok(TimeScale::Factor { factor }),
+        TimeScaleInput::Name(name) if name == "max" || name == "as_fast_as_possible" => {
+            Ok(TimeScale::AsFastAsPossible)
+        }
+        TimeScaleInput::Name(name) => Err(D::Error::custom(format!(
+            "unknown time_scale '{}', use a number or \"max\"",
+            name
+        ))),
+        TimeScaleInput::Spec(time_scale) => Ok(time_scale),
+    }
+}
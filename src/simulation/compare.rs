@@ -0,0 +1,142 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::models::{
+    ConfigDifference, MetricComparison, RunComparison, SimulationReport, TipSample,
+};
+
+/// Metrics compared between runs, in the order they are listed.
+pub(crate) fn metrics(report: &SimulationReport) -> Vec<(&'static str, f64)> {
+    let propagation = &report.propagation;
+    let confirmation = &report.confirmation;
+    let virtual_secs = report.virtual_duration_ms as f64 / 1000.0;
+    vec![
+        ("blocks_created", report.blocks_created as f64),
+        ("virtual_duration_ms", report.virtual_duration_ms as f64),
+        (
+            "throughput_blocks_per_sec",
+            if virtual_secs > 0.0 {
+                report.blocks_created as f64 / virtual_secs
+            } else {
+                0.0
+            },
+        ),
+        ("confirmation_mean_ms", confirmation.latency.mean_ms),
+        ("confirmation_p50_ms", confirmation.latency.p50_ms as f64),
+        ("confirmation_p90_ms", confirmation.latency.p90_ms as f64),
+        ("confirmation_p99_ms", confirmation.latency.p99_ms as f64),
+        ("unconfirmed_blocks", confirmation.unconfirmed_blocks as f64),
+        ("orphan_rate", confirmation.orphan_rate),
+        ("propagation_mean_ms", propagation.delay.mean_ms),
+        ("propagation_p99_ms", propagation.delay.p99_ms as f64),
+        (
+            "full_propagation_mean_ms",
+            propagation.full_propagation.mean_ms,
+        ),
+        ("incomplete_blocks", propagation.incomplete_blocks as f64),
+        ("mean_node_tips", mean_node_tips(&report.tip_counts)),
+        ("blocks_sent", report.blocks_sent as f64),
+        ("duplicate_blocks", report.duplicate_blocks as f64),
+        ("messages_lost", report.messages_lost as f64),
+        ("messages_partitioned", report.messages_partitioned as f64),
+        ("messages_offline", report.messages_offline as f64),
+    ]
+}
+
+fn mean_node_tips(samples: &[TipSample]) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    samples
+        .iter()
+        .map(|sample| sample.mean_node_tips)
+        .sum::<f64>()
+        / samples.len() as f64
+}
+
+impl RunComparison {
+    /// Compares `reports` against the first one.
+    pub fn new(reports: &[SimulationReport]) -> Self {
+        let per_run: Vec<Vec<(&str, f64)>> = reports.iter().map(metrics).collect();
+        let metrics = per_run
+            .first()
+            .map(|baseline| {
+                baseline
+                    .iter()
+                    .enumerate()
+                    .map(|(index, &(metric, base))| {
+                        let values: Vec<f64> = per_run.iter().map(|run| run[index].1).collect();
+                        MetricComparison {
+                            metric: metric.to_string(),
+                            differences: values.iter().map(|value| value - base).collect(),
+                            relative_differences: values
+                                .iter()
+                                .map(|value| (base != 0.0).then(|| (value - base) / base))
+                                .collect(),
+                            values,
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            runs: reports.iter().map(|report| report.run_id.clone()).collect(),
+            completed: reports.iter().map(|report| report.completed).collect(),
+            metrics,
+            config_diff: config_diff(reports),
+        }
+    }
+}
+
+/// Config fields, by dotted path, whose values are not the same in every run.
+/// Lists such as the timeline are compared as a whole.
+fn config_diff(reports: &[SimulationReport]) -> Vec<ConfigDifference> {
+    let configs: Vec<BTreeMap<String, Value>> = reports
+        .iter()
+        .map(|report| {
+            let mut fields = BTreeMap::new();
+            let config = serde_json::to_value(&report.config).unwrap_or(Value::Null);
+            flatten(String::new(), config, &mut fields);
+            fields
+        })
+        .collect();
+
+    let mut paths: Vec<&String> = configs.iter().flat_map(BTreeMap::keys).collect();
+    paths.sort();
+    paths.dedup();
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let values: Vec<Value> = configs
+                .iter()
+                .map(|fields| fields.get(path).cloned().unwrap_or(Value::Null))
+                .collect();
+            values
+                .iter()
+                .any(|value| value != &values[0])
+                .then(|| ConfigDifference {
+                    field: path.clone(),
+                    values,
+                })
+        })
+        .collect()
+}
+
+fn flatten(path: String, value: Value, fields: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(object) if !object.is_empty() => {
+            for (key, value) in object {
+                let field = if path.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                flatten(field, value, fields);
+            }
+        }
+        value => {
+            fields.insert(path, value);
+        }
+    }
+}
@@ -0,0 +1,1058 @@
+use chrono::Utc;
+use rand::{seq::index::sample, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, VecDeque},
+    sync::{Arc, RwLock},
+};
+use tokio::{
+    sync::{broadcast, mpsc, watch},
+    time::{sleep_until, Instant},
+};
+
+use super::adversary::Adversaries;
+use super::churn::{ChurnTracker, Downtime};
+use super::link::{LinkTable, Transmission};
+use super::node::{VirtualNode, DEFAULT_PARENT_COUNT};
+use super::report::ReportRecorder;
+use super::sensors::SensorGenerator;
+use super::store::{EventLog, RunStore};
+use super::trace::TraceReplay;
+use crate::models::{
+    RecordedCommand, RunRecord, SimulationCommand, SimulationConfig, SimulationEvent,
+    SimulationEventKind, SimulationStatus, SimulationUpdate, SimulationUpdateKind, TipSelection,
+    TraceEvent, TraceEventKind,
+};
+use ecoblock_core::SensorData;
+
+/// A block as seen by the simulated network.
+#[derive(Debug)]
+pub(crate) struct SimBlock {
+    pub id: u64,
+    pub parents: Vec<u64>,
+    pub created_at_ms: u64,
+    pub data: SensorData,
+    /// `false` for blocks signed by an adversary with an invalid signature.
+    pub valid_signature: bool,
+}
+
+/// Parents and reading of a block taken from a replayed trace.
+struct TracedBlock {
+    parents: Vec<u64>,
+    data: SensorData,
+}
+
+/// What a run is started from besides its config.
+#[derive(Default)]
+pub(crate) struct RunInputs {
+    /// Record of an earlier run to replay exactly.
+    pub replay: Option<RunRecord>,
+    pub scenario: Option<String>,
+    /// Events of the trace named by the config, replacing generated blocks.
+    pub trace: Vec<TraceEvent>,
+}
+
+enum EventKind {
+    /// The next block of the run is due.
+    Generate,
+    /// Index of the trace event that is due.
+    Trace(usize),
+    /// Index of the timeline step that is due.
+    Step(usize),
+    /// An adversarial node's next spam burst is due.
+    Spam(usize),
+    /// The node's current churn session or downtime is over.
+    Churn(usize),
+    Outage,
+    /// Nodes taken offline by an outage come back.
+    OutageEnd(Vec<usize>),
+    Deliver {
+        from: usize,
+        to: usize,
+        block: Arc<SimBlock>,
+        /// Resync the block was requested by, if it is not being gossiped.
+        resync: Option<usize>,
+    },
+}
+
+struct ScheduledEvent {
+    at_ms: u64,
+    seq: u64,
+    kind: EventKind,
+}
+
+// `BinaryHeap` is a max-heap: order events so the earliest (and, on ties, the
+// first scheduled) is popped first.
+impl Ord for ScheduledEvent {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.at_ms.cmp(&self.at_ms).then(other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for ScheduledEvent {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for ScheduledEvent {
+    fn eq(&self, other: &Self) -> bool {
+        self.at_ms == other.at_ms && self.seq == other.seq
+    }
+}
+
+impl Eq for ScheduledEvent {}
+
+/// Events run without waiting before the run yields to other tasks.
+const YIELD_EVERY_EVENTS: u64 = 1024;
+
+#[derive(Default)]
+struct Counters {
+    blocks_created: usize,
+    blocks_sent: usize,
+    blocks_received: usize,
+    duplicate_blocks: usize,
+    messages_lost: usize,
+    messages_partitioned: usize,
+    messages_offline: usize,
+}
+
+/// Discrete-event simulation of `node_count` virtual nodes gossiping blocks.
+///
+/// Events are kept on a virtual millisecond clock and executed as they fall
+/// due in wall-clock time, scaled by the config's `time_scale`, or back to
+/// back when running as fast as possible. Every random choice comes from one RNG seeded from
+/// the config, and commands are recorded at the point in the event sequence
+/// where they were applied, so a run can be replayed exactly.
+pub(crate) struct Simulation {
+    run_id: String,
+    replay_of: Option<String>,
+    scenario: Option<String>,
+    config: SimulationConfig,
+    nodes: Vec<VirtualNode>,
+    downtime: Vec<Downtime>,
+    /// Current block interval, changed by `block_rate` commands.
+    interval_ms: u64,
+    parent_count: usize,
+    /// Regular blocks created so far, out of the config's `block_count`.
+    generated: usize,
+    /// Set once every node is offline for good, which ends block creation
+    /// before `block_count` is reached.
+    generation_ended: bool,
+    next_origin: usize,
+    steps_applied: usize,
+    neighbours: Vec<Vec<usize>>,
+    links: LinkTable,
+    sensors: Vec<SensorGenerator>,
+    adversaries: Adversaries,
+    churn: ChurnTracker,
+    trace: TraceReplay,
+    /// Pairs of conflicting blocks created by adversaries.
+    conflicts: Vec<(u64, u64)>,
+    /// Virtual wall-clock time the run started, as Unix milliseconds, used
+    /// to timestamp generated sensor readings.
+    epoch_ms: u64,
+    rng: ChaCha8Rng,
+    queue: BinaryHeap<ScheduledEvent>,
+    next_seq: u64,
+    now_ms: u64,
+    events_processed: u64,
+    /// Commands still to apply when replaying a recorded run.
+    script: VecDeque<RecordedCommand>,
+    stop_after_events: Option<u64>,
+    commands: Vec<RecordedCommand>,
+    event_log: EventLog,
+    counters: Counters,
+    last_reading: Option<SensorData>,
+    report: ReportRecorder,
+    store: RunStore,
+    status: Arc<RwLock<SimulationStatus>>,
+    updates: broadcast::Sender<SimulationUpdate>,
+}
+
+impl Simulation {
+    /// Prepares a run of `config`, filling in a random seed and the current
+    /// time as epoch where they are missing. Passing the record of an earlier
+    /// run replays its commands and stop point; passing a trace replays its
+    /// blocks and peer changes instead of generating blocks.
+    pub fn new(
+        run_id: String,
+        mut config: SimulationConfig,
+        inputs: RunInputs,
+        store: RunStore,
+        status: Arc<RwLock<SimulationStatus>>,
+        updates: broadcast::Sender<SimulationUpdate>,
+    ) -> Self {
+        let seed = *config.seed.get_or_insert_with(rand::random);
+        let epoch = *config.epoch.get_or_insert_with(Utc::now);
+        let node_count = config.node_count;
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let nodes = (0..node_count).map(|_| VirtualNode::new()).collect();
+        let neighbours = config.topology.generate(node_count, &mut rng);
+        let links = LinkTable::new(config.link.clone(), &config.link_overrides);
+        let sensors = (0..node_count)
+            .map(|node| SensorGenerator::new(config.sensor_profile_for(node).clone(), rng.gen()))
+            .collect();
+        let adversaries = Adversaries::new(config.adversaries.as_ref(), node_count, &mut rng);
+        let report = ReportRecorder::new(adversaries.flags());
+
+        let (replay_of, script, stop_after_events) = match inputs.replay {
+            Some(record) => (
+                Some(record.run_id),
+                record.commands.into(),
+                record.stopped_after_events,
+            ),
+            None => (None, VecDeque::new(), None),
+        };
+
+        let mut simulation = Self {
+            event_log: store.event_log(&run_id),
+            run_id,
+            replay_of,
+            scenario: inputs.scenario,
+            interval_ms: config.interval_ms,
+            parent_count: config.parent_count.unwrap_or(DEFAULT_PARENT_COUNT),
+            config,
+            nodes,
+            downtime: vec![Downtime::default(); node_count],
+            generated: 0,
+            generation_ended: false,
+            next_origin: 0,
+            steps_applied: 0,
+            neighbours,
+            links,
+            sensors,
+            adversaries,
+            churn: ChurnTracker::new(node_count),
+            trace: TraceReplay::new(inputs.trace, node_count),
+            conflicts: Vec::new(),
+            epoch_ms: epoch.timestamp_millis().max(0) as u64,
+            rng,
+            queue: BinaryHeap::new(),
+            next_seq: 0,
+            now_ms: 0,
+            events_processed: 0,
+            script,
+            stop_after_events,
+            commands: Vec::new(),
+            counters: Counters::default(),
+            last_reading: None,
+            report,
+            store,
+            status,
+            updates,
+        };
+        if simulation.trace.is_empty() {
+            simulation.schedule(0, EventKind::Generate);
+        } else {
+            let times: Vec<u64> = simulation
+                .trace
+                .events
+                .iter()
+                .map(|event| event.at_ms)
+                .collect();
+            for (index, at_ms) in times.into_iter().enumerate() {
+                simulation.schedule(at_ms, EventKind::Trace(index));
+            }
+        }
+        let steps: Vec<u64> = simulation
+            .config
+            .timeline
+            .iter()
+            .map(|step| step.at_ms)
+            .collect();
+        for (index, at_ms) in steps.into_iter().enumerate() {
+            simulation.schedule(at_ms, EventKind::Step(index));
+        }
+        if let Some((_, every_ms)) = simulation.adversaries.spam {
+            for node in simulation.adversaries.nodes() {
+                simulation.schedule(every_ms, EventKind::Spam(node));
+            }
+        }
+        if let Some(churn) = simulation.config.churn.clone() {
+            for node in 0..node_count {
+                let session = churn.session.sample(&mut simulation.rng);
+                simulation.schedule(session, EventKind::Churn(node));
+            }
+            if let Some(outages) = churn.outages {
+                let next = outages.every.sample(&mut simulation.rng);
+                simulation.schedule(next, EventKind::Outage);
+            }
+        }
+        simulation
+    }
+
+    /// Runs until every block has been created and delivered, or until `stop`
+    /// is signalled. Commands are applied at the virtual time they arrive.
+    pub async fn run(
+        mut self,
+        mut commands: mpsc::UnboundedReceiver<SimulationCommand>,
+        mut stop: watch::Receiver<bool>,
+    ) {
+        let started = Instant::now();
+        {
+            let mut status = self.status.write().unwrap();
+            *status = SimulationStatus {
+                run_id: Some(self.run_id.clone()),
+                replay_of: self.replay_of.clone(),
+                seed: self.config.seed,
+                scenario: self.scenario.clone(),
+                timeline_steps: self.config.timeline.len(),
+                adversarial_nodes: self.adversaries.nodes(),
+                is_running: true,
+                node_count: self.config.node_count,
+                start_time: Some(Utc::now()),
+                ..SimulationStatus::default()
+            };
+        }
+        self.publish(SimulationUpdateKind::RunStarted {
+            node_count: self.config.node_count,
+            scenario: self.scenario.clone(),
+            replay_of: self.replay_of.clone(),
+        });
+
+        let mut stopped = false;
+        loop {
+            while self
+                .script
+                .front()
+                .is_some_and(|recorded| recorded.after_events == self.events_processed)
+            {
+                let recorded = self.script.pop_front().expect("checked front");
+                self.now_ms = recorded.at_ms;
+                self.apply_recorded(recorded.command);
+            }
+            if self.stop_after_events == Some(self.events_processed) {
+                stopped = true;
+                break;
+            }
+            let Some(next_at) = self.queue.peek().map(|event| event.at_ms) else {
+                break;
+            };
+
+            let due = self.config.time_scale.due(started, next_at);
+            if due <= Instant::now() {
+                // Overdue events run straight away: a timer would round each
+                // wait up to a whole millisecond.
+                if stop.has_changed().unwrap_or(true) {
+                    stopped = true;
+                    break;
+                }
+                match commands.try_recv() {
+                    Ok(command) => self.receive_command(started, next_at, command),
+                    Err(_) => self.run_next_event(),
+                }
+                if self.events_processed.is_multiple_of(YIELD_EVERY_EVENTS) {
+                    tokio::task::yield_now().await;
+                }
+            } else {
+                tokio::select! {
+                    _ = sleep_until(due) => self.run_next_event(),
+                    Some(command) = commands.recv() => {
+                        self.receive_command(started, next_at, command);
+                    }
+                    _ = stop.changed() => {
+                        stopped = true;
+                        break;
+                    }
+                }
+            }
+            self.publish_status(started);
+        }
+
+        self.publish_status(started);
+        let status = {
+            let mut status = self.status.write().unwrap();
+            status.is_running = false;
+            status.end_time = Some(Utc::now());
+            status.clone()
+        };
+
+        self.publish(SimulationUpdateKind::RunFinished {
+            completed: !stopped,
+            status: Box::new(status.clone()),
+        });
+
+        let honest: Vec<&VirtualNode> = (0..self.nodes.len())
+            .filter(|&node| !self.adversaries.is_adversarial(node))
+            .map(|node| &self.nodes[node])
+            .collect();
+        self.adversaries.tally.honest_nodes_with_conflicts = honest
+            .iter()
+            .filter(|node| {
+                self.conflicts
+                    .iter()
+                    .any(|&(block, twin)| node.has(block) && node.has(twin))
+            })
+            .count();
+        self.adversaries.tally.unresolved_parents =
+            honest.iter().map(|node| node.missing_count()).sum();
+
+        let churn = self.churn.report(self.config.churn.is_some(), self.now_ms);
+
+        let (events, event_log_digest) = self.event_log.finish();
+        let record = RunRecord {
+            run_id: self.run_id,
+            config: self.config,
+            replay_of: self.replay_of,
+            commands: self.commands,
+            stopped_after_events: stopped.then_some(self.events_processed),
+            events,
+            event_log_digest,
+        };
+        if let Err(e) = self.store.save_run(&record) {
+            log::warn!("Failed to save simulation run {}: {}", record.run_id, e);
+        }
+
+        let report = self
+            .report
+            .finish(&record, &status, !stopped, &self.adversaries, churn);
+        if let Err(e) = self.store.save_report(&report) {
+            log::warn!(
+                "Failed to save report for simulation {}: {}",
+                report.run_id,
+                e
+            );
+        }
+    }
+
+    fn run_next_event(&mut self) {
+        let event = self.queue.pop().expect("peeked event");
+        self.now_ms = event.at_ms;
+        self.events_processed += 1;
+        self.handle(event.kind);
+    }
+
+    /// Applies a command received while the event due at `next_at` was
+    /// pending.
+    fn receive_command(&mut self, started: Instant, next_at: u64, command: SimulationCommand) {
+        // Never move the clock past the next event, which may not have been
+        // picked up yet.
+        let elapsed = self.config.time_scale.virtual_elapsed(started, self.now_ms);
+        self.now_ms = self.now_ms.max(elapsed.min(next_at));
+        self.apply_recorded(command);
+    }
+
+    /// Schedules `kind` `delay_ms` after the current virtual time. Delays
+    /// come from configs, so the clock saturates rather than wrapping.
+    fn schedule_after(&mut self, delay_ms: u64, kind: EventKind) {
+        self.schedule(self.now_ms.saturating_add(delay_ms), kind);
+    }
+
+    fn schedule(&mut self, at_ms: u64, kind: EventKind) {
+        self.queue.push(ScheduledEvent {
+            at_ms,
+            seq: self.next_seq,
+            kind,
+        });
+        self.next_seq += 1;
+    }
+
+    fn handle(&mut self, kind: EventKind) {
+        match kind {
+            EventKind::Generate => self.generate_block(),
+            EventKind::Trace(index) => self.replay_trace_event(index),
+            EventKind::Step(index) => {
+                let command = self.config.timeline[index].command.clone();
+                self.apply(command);
+                self.steps_applied += 1;
+            }
+            EventKind::Spam(node) => self.spam(node),
+            EventKind::Churn(node) => self.churn(node),
+            EventKind::Outage => self.outage(),
+            EventKind::OutageEnd(nodes) => {
+                for node in nodes {
+                    self.set_downtime(node, |downtime| downtime.outages -= 1);
+                }
+            }
+            EventKind::Deliver {
+                from,
+                to,
+                block,
+                resync,
+            } => self.deliver(from, to, block, resync),
+        }
+    }
+
+    /// Applies a command that did not come from the config's timeline,
+    /// recording it so a replay can apply it at the same point.
+    fn apply_recorded(&mut self, command: SimulationCommand) {
+        self.commands.push(RecordedCommand {
+            after_events: self.events_processed,
+            at_ms: self.now_ms,
+            command: command.clone(),
+        });
+        self.apply(command);
+    }
+
+    fn apply(&mut self, command: SimulationCommand) {
+        match &command {
+            SimulationCommand::Partition { groups } => {
+                self.links.partition(self.nodes.len(), groups)
+            }
+            SimulationCommand::Heal => self.links.heal(),
+            SimulationCommand::NodeLeave { nodes } => {
+                for &node in nodes {
+                    self.set_downtime(node, |downtime| downtime.left = true);
+                }
+            }
+            SimulationCommand::NodeJoin { nodes } => {
+                for &node in nodes {
+                    self.set_downtime(node, |downtime| downtime.left = false);
+                }
+            }
+            SimulationCommand::BlockRate { factor } => {
+                self.interval_ms = ((self.interval_ms as f64 / factor).round() as u64).max(1);
+            }
+        }
+        self.publish(SimulationUpdateKind::CommandApplied {
+            command: command.clone(),
+        });
+        self.log(SimulationEventKind::CommandApplied { command });
+    }
+
+    fn is_online(&self, node: usize) -> bool {
+        self.downtime[node].is_online()
+    }
+
+    /// Whether the run's regular blocks are still being created.
+    fn generating(&self) -> bool {
+        !self.generation_ended && self.generated < self.config.block_count
+    }
+
+    /// Whether an offline node may still come back. Churn sessions and
+    /// outages always end, but a node that left or was disconnected needs a
+    /// pending `node_join` step or trace reconnect.
+    fn may_come_online(&self) -> bool {
+        self.downtime
+            .iter()
+            .any(|downtime| !downtime.left && !downtime.disconnected)
+            || self.queue.iter().any(|event| match event.kind {
+                EventKind::Step(index) => matches!(
+                    self.config.timeline[index].command,
+                    SimulationCommand::NodeJoin { .. }
+                ),
+                EventKind::Trace(index) => matches!(
+                    self.trace.events[index].kind,
+                    TraceEventKind::PeerConnected { .. }
+                ),
+                _ => false,
+            })
+    }
+
+    /// Updates why `node` is offline, handling it leaving or coming back.
+    fn set_downtime(&mut self, node: usize, change: impl FnOnce(&mut Downtime)) {
+        let was_online = self.is_online(node);
+        change(&mut self.downtime[node]);
+        match (was_online, self.is_online(node)) {
+            (true, false) => {
+                self.churn.went_offline(node, self.now_ms);
+                self.log(SimulationEventKind::NodeOffline { node });
+            }
+            (false, true) => {
+                self.churn.came_online(node, self.now_ms);
+                self.log(SimulationEventKind::NodeOnline { node });
+                self.resync(node);
+            }
+            _ => {}
+        }
+    }
+
+    /// Ends `node`'s current churn session or downtime. Nodes only leave
+    /// while blocks are being created, but always come back.
+    fn churn(&mut self, node: usize) {
+        let Some(churn) = &self.config.churn else {
+            return;
+        };
+        if self.downtime[node].churned {
+            let session = churn.session.sample(&mut self.rng);
+            self.set_downtime(node, |downtime| downtime.churned = false);
+            if self.generating() {
+                self.schedule_after(session, EventKind::Churn(node));
+            }
+        } else if self.generating() {
+            let downtime = churn.downtime.sample(&mut self.rng);
+            self.set_downtime(node, |downtime| downtime.churned = true);
+            self.schedule_after(downtime, EventKind::Churn(node));
+        }
+    }
+
+    /// Takes a random share of the nodes offline at once.
+    fn outage(&mut self) {
+        let Some(outages) = self
+            .config
+            .churn
+            .as_ref()
+            .and_then(|churn| churn.outages.as_ref())
+        else {
+            return;
+        };
+        if !self.generating() {
+            return;
+        }
+        let node_count = self.nodes.len();
+        let count = ((outages.fraction * node_count as f64).round() as usize).min(node_count);
+        let duration = outages.duration.sample(&mut self.rng);
+        let next = outages.every.sample(&mut self.rng);
+        let mut nodes = sample(&mut self.rng, node_count, count).into_vec();
+        nodes.sort_unstable();
+
+        self.churn.outages += 1;
+        for &node in &nodes {
+            self.set_downtime(node, |downtime| downtime.outages += 1);
+        }
+        self.schedule_after(duration, EventKind::OutageEnd(nodes));
+        self.schedule_after(next, EventKind::Outage);
+    }
+
+    /// Asks a random reachable neighbour for the blocks `node` missed while
+    /// it was offline.
+    fn resync(&mut self, node: usize) {
+        let peers: Vec<usize> = self.neighbours[node]
+            .iter()
+            .copied()
+            .filter(|&peer| self.is_online(peer) && self.links.can_reach(peer, node))
+            .collect();
+        if peers.is_empty() {
+            return;
+        }
+        let peer = peers[self.rng.gen_range(0..peers.len())];
+        let missed = self.nodes[peer].blocks_missing_from(&self.nodes[node]);
+        let resync = self.churn.resync_started(missed.len(), self.now_ms);
+        self.log(SimulationEventKind::ResyncRequested {
+            node,
+            from: peer,
+            blocks: missed.len(),
+        });
+        for block in &missed {
+            self.send(peer, node, block, Some(resync));
+        }
+    }
+
+    fn log(&mut self, kind: SimulationEventKind) {
+        self.event_log.record(&SimulationEvent {
+            at_ms: self.now_ms,
+            kind,
+        });
+    }
+
+    /// Sends a progress update to live subscribers, if there are any.
+    fn publish(&self, kind: SimulationUpdateKind) {
+        let _ = self.updates.send(SimulationUpdate {
+            run_id: self.run_id.clone(),
+            at_ms: self.now_ms,
+            kind,
+        });
+    }
+
+    /// Picks the node to create the next block: blocks are created
+    /// round-robin by the nodes that are online.
+    fn next_origin(&mut self) -> Option<usize> {
+        let node_count = self.nodes.len();
+        let origin = (0..node_count)
+            .map(|offset| (self.next_origin + offset) % node_count)
+            .find(|&node| self.is_online(node))?;
+        self.next_origin = origin + 1;
+        Some(origin)
+    }
+
+    fn generate_block(&mut self) {
+        let Some(origin) = self.next_origin() else {
+            if self.may_come_online() {
+                self.schedule_after(self.interval_ms, EventKind::Generate);
+            } else {
+                self.generation_ended = true;
+            }
+            return;
+        };
+        self.generated += 1;
+        self.create_block(origin, None);
+
+        if self.generated < self.config.block_count {
+            self.schedule_after(self.interval_ms, EventKind::Generate);
+        }
+    }
+
+    fn replay_trace_event(&mut self, index: usize) {
+        match self.trace.events[index].kind.clone() {
+            TraceEventKind::Block { id, parents, data } => {
+                // Blocks no node can ever create are dropped.
+                let Some(origin) = self.next_origin() else {
+                    if self.may_come_online() {
+                        self.schedule_after(self.interval_ms, EventKind::Trace(index));
+                    }
+                    return;
+                };
+                self.generated += 1;
+                let parents = self.trace.parents(&parents);
+                let block = self.create_block(origin, Some(TracedBlock { parents, data }));
+                self.trace.block_replayed(id, block.id);
+            }
+            TraceEventKind::PeerConnected { peer, .. } => {
+                if let Some(node) = self.trace.connect(&peer) {
+                    self.set_downtime(node, |downtime| downtime.disconnected = false);
+                }
+            }
+            TraceEventKind::PeerDisconnected { peer } => {
+                if let Some(node) = self.trace.node(&peer) {
+                    self.set_downtime(node, |downtime| downtime.disconnected = true);
+                }
+            }
+        }
+    }
+
+    /// Spam bursts continue for as long as regular blocks are being created.
+    fn spam(&mut self, node: usize) {
+        let Some((blocks, every_ms)) = self.adversaries.spam else {
+            return;
+        };
+        if !self.generating() {
+            return;
+        }
+        if self.is_online(node) {
+            for _ in 0..blocks {
+                self.create_block(node, None);
+                self.adversaries.tally.spam_blocks += 1;
+            }
+        }
+        self.schedule_after(every_ms, EventKind::Spam(node));
+    }
+
+    /// Parents for a new block on `origin` under the run's tip selection.
+    /// Blocks without recorded parents fall back to the latest tips.
+    fn select_parents(&mut self, origin: usize, recorded: Option<Vec<u64>>) -> Vec<u64> {
+        match (self.config.tip_selection, recorded) {
+            (TipSelection::Recorded, Some(parents)) => parents,
+            (TipSelection::Random, _) => {
+                self.nodes[origin].select_random(self.parent_count, &mut self.rng)
+            }
+            _ => self.nodes[origin].select_parents(self.parent_count),
+        }
+    }
+
+    /// Creates a block on `origin` and gossips it, misbehaving as configured
+    /// if the origin is adversarial. Returns the block, or the first of a
+    /// pair of conflicting blocks.
+    fn create_block(&mut self, origin: usize, traced: Option<TracedBlock>) -> Arc<SimBlock> {
+        let (recorded, data) = match traced {
+            Some(traced) => (Some(traced.parents), Some(traced.data)),
+            None => (None, None),
+        };
+        let adversarial = self.adversaries.is_adversarial(origin);
+        let mut parents = if adversarial && self.adversaries.lazy_tips {
+            self.nodes[origin].select_oldest(self.parent_count)
+        } else {
+            self.select_parents(origin, recorded)
+        };
+        if adversarial && self.adversaries.withhold_parents {
+            // Withheld parents are never published; their IDs count down from
+            // the top so they can't clash with published blocks.
+            parents.truncate(self.parent_count - 1);
+            parents.push(u64::MAX - self.adversaries.tally.withheld_parents as u64);
+            self.adversaries.tally.withheld_parents += 1;
+        }
+        let valid_signature = !(adversarial && self.adversaries.invalid_signatures);
+
+        let block = self.new_block(origin, parents.clone(), valid_signature, data.clone());
+        if adversarial && self.adversaries.conflicting_blocks {
+            let twin = self.new_block(origin, parents, valid_signature, data);
+            self.adversaries.tally.conflicting_blocks += 1;
+            self.conflicts.push((block.id, twin.id));
+
+            let targets = self.neighbours[origin].clone();
+            let (first, second) = targets.split_at(targets.len().div_ceil(2));
+            self.send_all(origin, first, &block);
+            self.send_all(origin, second, &twin);
+        } else {
+            self.gossip(origin, None, &block);
+        }
+        block
+    }
+
+    /// Adds a new block to `origin`'s tangle and records its creation. The
+    /// reading is sampled from the origin's sensors unless one is given.
+    fn new_block(
+        &mut self,
+        origin: usize,
+        parents: Vec<u64>,
+        valid_signature: bool,
+        data: Option<SensorData>,
+    ) -> Arc<SimBlock> {
+        let data = match data {
+            Some(data) => data,
+            None => self.sensors[origin].sample(self.epoch_ms.saturating_add(self.now_ms)),
+        };
+        let block = Arc::new(SimBlock {
+            id: self.counters.blocks_created as u64,
+            parents,
+            created_at_ms: self.now_ms,
+            data,
+            valid_signature,
+        });
+        self.counters.blocks_created += 1;
+        self.last_reading = Some(block.data.clone());
+
+        self.nodes[origin].receive(block.clone());
+        self.log(SimulationEventKind::BlockCreated {
+            block: block.id,
+            origin,
+            parents: block.parents.clone(),
+        });
+        self.report.block_created(origin, &block);
+        self.publish(SimulationUpdateKind::BlockCreated {
+            node: origin,
+            block: block.id,
+            parents: block.parents.clone(),
+        });
+        let mean_node_tips = self.nodes.iter().map(VirtualNode::tip_count).sum::<usize>() as f64
+            / self.nodes.len() as f64;
+        let honest_tips: Vec<usize> = (0..self.nodes.len())
+            .filter(|&node| !self.adversaries.is_adversarial(node))
+            .map(|node| self.nodes[node].tip_count())
+            .collect();
+        let mean_honest_tips = if honest_tips.is_empty() {
+            0.0
+        } else {
+            honest_tips.iter().sum::<usize>() as f64 / honest_tips.len() as f64
+        };
+        self.report
+            .sample_tips(self.now_ms, mean_node_tips, mean_honest_tips);
+        block
+    }
+
+    fn deliver(&mut self, from: usize, to: usize, block: Arc<SimBlock>, resync: Option<usize>) {
+        // A partition applied while the message was in flight cuts it off too.
+        if !self.is_online(to) {
+            self.counters.messages_offline += 1;
+            self.log(SimulationEventKind::MessageOffline {
+                from,
+                to,
+                block: block.id,
+            });
+            return;
+        }
+        if !self.links.can_reach(from, to) {
+            self.counters.messages_partitioned += 1;
+            self.log(SimulationEventKind::MessagePartitioned {
+                from,
+                to,
+                block: block.id,
+            });
+            return;
+        }
+        if let Some(resync) = resync {
+            self.churn.resync_block(resync, self.now_ms);
+        }
+
+        if !block.valid_signature && !self.adversaries.is_adversarial(to) {
+            self.adversaries.tally.rejected_blocks += 1;
+            self.log(SimulationEventKind::BlockRejected {
+                from,
+                to,
+                block: block.id,
+            });
+            return;
+        }
+
+        self.counters.blocks_received += 1;
+        let is_new = self.nodes[to].receive(block.clone());
+        self.log(SimulationEventKind::BlockDelivered {
+            from,
+            to,
+            block: block.id,
+            duplicate: !is_new,
+            resync: resync.is_some(),
+        });
+        self.report.message_received(to, !is_new);
+        if is_new {
+            if self.report.block_reached(&block, to, self.now_ms) {
+                self.publish(SimulationUpdateKind::PropagationComplete {
+                    block: block.id,
+                    delay_ms: self.now_ms - block.created_at_ms,
+                });
+            }
+            // Blocks caught up on after rejoining are not news to the network.
+            if resync.is_none() {
+                self.gossip(to, Some(from), &block);
+            }
+        } else {
+            self.counters.duplicate_blocks += 1;
+        }
+    }
+
+    /// Forwards `block` from `node` to all of its neighbours except the one
+    /// it came from.
+    fn gossip(&mut self, node: usize, from: Option<usize>, block: &Arc<SimBlock>) {
+        let targets: Vec<usize> = self.neighbours[node]
+            .iter()
+            .copied()
+            .filter(|&peer| Some(peer) != from)
+            .collect();
+        self.send_all(node, &targets, block);
+    }
+
+    /// Sends `block` from `node` to each of `targets`, repeatedly if `node`
+    /// is an adversary sending duplicates.
+    fn send_all(&mut self, node: usize, targets: &[usize], block: &Arc<SimBlock>) {
+        let copies = if self.adversaries.is_adversarial(node) {
+            1 + self.adversaries.duplicate_copies
+        } else {
+            1
+        };
+        for &to in targets {
+            for copy in 0..copies {
+                if copy > 0 {
+                    self.adversaries.tally.duplicate_messages += 1;
+                }
+                self.send(node, to, block, None);
+            }
+        }
+    }
+
+    fn send(&mut self, node: usize, to: usize, block: &Arc<SimBlock>, resync: Option<usize>) {
+        self.counters.blocks_sent += 1;
+        self.report.message_sent(node);
+        match self.links.transmit(node, to, self.now_ms, &mut self.rng) {
+            Transmission::Delivered { arrival_ms } => self.schedule(
+                arrival_ms,
+                EventKind::Deliver {
+                    from: node,
+                    to,
+                    block: block.clone(),
+                    resync,
+                },
+            ),
+            Transmission::Lost => {
+                self.counters.messages_lost += 1;
+                self.log(SimulationEventKind::MessageLost {
+                    from: node,
+                    to,
+                    block: block.id,
+                });
+            }
+            Transmission::Partitioned => {
+                self.counters.messages_partitioned += 1;
+                self.log(SimulationEventKind::MessagePartitioned {
+                    from: node,
+                    to,
+                    block: block.id,
+                });
+            }
+        }
+    }
+
+    fn publish_status(&self, started: Instant) {
+        let mut status = self.status.write().unwrap();
+        status.blocks_created = self.counters.blocks_created;
+        status.blocks_sent = self.counters.blocks_sent;
+        status.blocks_received = self.counters.blocks_received;
+        status.duplicate_blocks = self.counters.duplicate_blocks;
+        status.messages_lost = self.counters.messages_lost;
+        status.messages_partitioned = self.counters.messages_partitioned;
+        status.messages_offline = self.counters.messages_offline;
+        status.offline_nodes = (0..self.nodes.len())
+            .filter(|&node| !self.is_online(node))
+            .collect();
+        status.timeline_steps_applied = self.steps_applied;
+        status.progress = (self.generated + self.steps_applied) as f64
+            / (self.config.block_count + self.config.timeline.len()) as f64;
+        status.is_partitioned = self.links.is_partitioned();
+        status.last_reading = self.last_reading.clone();
+        status.virtual_time_ms = self.now_ms;
+        status.duration = Some(started.elapsed().as_millis() as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        AdversaryBehaviour, AdversaryConfig, ChurnConfig, DurationDistribution, OutageModel,
+        TimeScale,
+    };
+    use std::time::Duration;
+
+    /// Runs `config` as fast as possible until it ends and returns its final
+    /// status, failing if the run does not end.
+    async fn run_to_end(name: &str, config: SimulationConfig) -> SimulationStatus {
+        let dir =
+            std::env::temp_dir().join(format!("ecoblock-engine-{}-{}", name, std::process::id()));
+        let status = Arc::new(RwLock::new(SimulationStatus::default()));
+        let simulation = Simulation::new(
+            name.to_string(),
+            SimulationConfig {
+                time_scale: TimeScale::AsFastAsPossible,
+                seed: Some(7),
+                ..config
+            },
+            RunInputs::default(),
+            RunStore::new(&dir),
+            status.clone(),
+            broadcast::channel(16).0,
+        );
+        let (_commands, command_receiver) = mpsc::unbounded_channel();
+        let (_stop, stop_receiver) = watch::channel(false);
+
+        let run = simulation.run(command_receiver, stop_receiver);
+        let finished = tokio::time::timeout(Duration::from_secs(30), run).await;
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(finished.is_ok(), "run did not end");
+        let status = status.read().unwrap().clone();
+        status
+    }
+
+    fn fixed(ms: u64) -> DurationDistribution {
+        DurationDistribution::Fixed { ms }
+    }
+
+    #[tokio::test]
+    async fn huge_block_interval_saturates_the_clock() {
+        let status = run_to_end(
+            "interval",
+            SimulationConfig {
+                node_count: 4,
+                block_count: 3,
+                interval_ms: u64::MAX,
+                ..SimulationConfig::default()
+            },
+        )
+        .await;
+
+        assert_eq!(status.blocks_created, 3);
+        assert_eq!(status.virtual_time_ms, u64::MAX);
+    }
+
+    #[tokio::test]
+    async fn huge_churn_outage_and_spam_delays_saturate_the_clock() {
+        let status = run_to_end(
+            "churn",
+            SimulationConfig {
+                node_count: 4,
+                block_count: 3,
+                interval_ms: u64::MAX,
+                churn: Some(ChurnConfig {
+                    session: fixed(u64::MAX),
+                    downtime: fixed(u64::MAX),
+                    outages: Some(OutageModel {
+                        every: fixed(u64::MAX),
+                        duration: fixed(u64::MAX),
+                        fraction: 0.5,
+                    }),
+                }),
+                adversaries: Some(AdversaryConfig {
+                    fraction: 0.5,
+                    behaviours: vec![AdversaryBehaviour::SpamBurst {
+                        blocks: 1,
+                        every_ms: u64::MAX,
+                    }],
+                }),
+                ..SimulationConfig::default()
+            },
+        )
+        .await;
+
+        assert!(!status.is_running);
+        assert_eq!(status.virtual_time_ms, u64::MAX);
+    }
+}
@@ -0,0 +1,174 @@
+use rand::Rng;
+use std::collections::HashMap;
+
+use super::{standard_normal, SimulationError};
+use crate::models::{LatencyModel, LinkModel, LinkOverride};
+
+/// Approximate size of a serialized block on the wire.
+pub(crate) const BLOCK_SIZE_BYTES: u64 = 512;
+
+impl LinkModel {
+    pub fn validate(&self) -> Result<(), SimulationError> {
+        if !(0.0..=1.0).contains(&self.loss_rate) {
+            return Err(SimulationError::InvalidConfig(
+                "loss_rate must be between 0 and 1".to_string(),
+            ));
+        }
+        if self.bandwidth_bps == Some(0) {
+            return Err(SimulationError::InvalidConfig(
+                "bandwidth_bps must be greater than 0".to_string(),
+            ));
+        }
+        if let LatencyModel::Normal {
+            mean_ms,
+            std_dev_ms,
+        } = self.latency
+        {
+            if !(mean_ms.is_finite() && std_dev_ms.is_finite()) || mean_ms < 0.0 || std_dev_ms < 0.0
+            {
+                return Err(SimulationError::InvalidConfig(
+                    "latency mean_ms and std_dev_ms must be finite and not negative".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn sample_delay<R: Rng>(&self, rng: &mut R) -> u64 {
+        let latency = match self.latency {
+            LatencyModel::Fixed { ms } => ms,
+            LatencyModel::Normal {
+                mean_ms,
+                std_dev_ms,
+            } => (mean_ms + std_dev_ms * standard_normal(rng))
+                .max(0.0)
+                .round() as u64,
+        };
+        let jitter = if self.jitter_ms > 0 {
+            rng.gen_range(0..=self.jitter_ms)
+        } else {
+            0
+        };
+        latency.saturating_add(jitter)
+    }
+
+    fn transmission_ms(&self) -> u64 {
+        match self.bandwidth_bps {
+            Some(bps) => (BLOCK_SIZE_BYTES * 8 * 1000).div_ceil(bps),
+            None => 0,
+        }
+    }
+}
+
+/// Outcome of putting a message on a link.
+pub(crate) enum Transmission {
+    Delivered { arrival_ms: u64 },
+    Lost,
+    Partitioned,
+}
+
+/// Per-link conditions and state (queueing, partitions) for a running simulation.
+pub(crate) struct LinkTable {
+    default: LinkModel,
+    overrides: HashMap<(usize, usize), LinkModel>,
+    /// Time each directed link finishes sending its queued messages.
+    busy_until: HashMap<(usize, usize), u64>,
+    /// Partition group of every node, or `None` when the network is whole.
+    groups: Option<Vec<usize>>,
+}
+
+impl LinkTable {
+    pub fn new(default: LinkModel, overrides: &[LinkOverride]) -> Self {
+        let overrides = overrides
+            .iter()
+            .map(|link| ((link.a.min(link.b), link.a.max(link.b)), link.model.clone()))
+            .collect();
+
+        Self {
+            default,
+            overrides,
+            busy_until: HashMap::new(),
+            groups: None,
+        }
+    }
+
+    fn model(&self, from: usize, to: usize) -> &LinkModel {
+        self.overrides
+            .get(&(from.min(to), from.max(to)))
+            .unwrap_or(&self.default)
+    }
+
+    pub fn is_partitioned(&self) -> bool {
+        self.groups.is_some()
+    }
+
+    pub fn can_reach(&self, from: usize, to: usize) -> bool {
+        match &self.groups {
+            Some(groups) => groups[from] == groups[to],
+            None => true,
+        }
+    }
+
+    /// Splits the nodes into `groups`; unlisted nodes share one extra group.
+    pub fn partition(&mut self, node_count: usize, groups: &[Vec<usize>]) {
+        let mut assignment = vec![groups.len(); node_count];
+        for (group, nodes) in groups.iter().enumerate() {
+            for &node in nodes {
+                assignment[node] = group;
+            }
+        }
+        self.groups = Some(assignment);
+    }
+
+    pub fn heal(&mut self) {
+        self.groups = None;
+    }
+
+    pub fn transmit<R: Rng>(
+        &mut self,
+        from: usize,
+        to: usize,
+        now_ms: u64,
+        rng: &mut R,
+    ) -> Transmission {
+        if !self.can_reach(from, to) {
+            return Transmission::Partitioned;
+        }
+
+        let model = self.model(from, to).clone();
+        let busy_until = self.busy_until.entry((from, to)).or_insert(0);
+        let departure = now_ms
+            .max(*busy_until)
+            .saturating_add(model.transmission_ms());
+        *busy_until = departure;
+
+        if model.loss_rate > 0.0 && rng.gen_bool(model.loss_rate) {
+            return Transmission::Lost;
+        }
+        Transmission::Delivered {
+            arrival_ms: departure.saturating_add(model.sample_delay(rng)),
+        }
+    }
+}
+
+pub(crate) fn validate_partition(
+    node_count: usize,
+    groups: &[Vec<usize>],
+) -> Result<(), SimulationError> {
+    let mut seen = vec![false; node_count];
+    for &node in groups.iter().flatten() {
+        if node >= node_count {
+            return Err(SimulationError::InvalidConfig(format!(
+                "node {} is out of range for {} nodes",
+                node, node_count
+            )));
+        }
+        if std::mem::replace(&mut seen[node], true) {
+            return Err(SimulationError::InvalidConfig(format!(
+                "node {} is listed in more than one group",
+                node
+            )));
+        }
+    }
+    Ok(())
+}
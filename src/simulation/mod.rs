@@ -0,0 +1,674 @@
+mod adversary;
+mod churn;
+mod clock;
+mod compare;
+mod engine;
+mod link;
+mod node;
+mod report;
+mod scenario;
+mod sensors;
+mod store;
+mod sweep;
+mod topology;
+mod trace;
+
+use chrono::Utc;
+use rand::Rng;
+use std::cmp::Reverse;
+use std::sync::{Arc, Mutex, RwLock};
+use thiserror::Error;
+use tokio::{
+    sync::{broadcast, mpsc, watch},
+    task::JoinHandle,
+};
+use uuid::Uuid;
+
+use crate::models::{
+    LinkModel, RunComparison, RunSummary, Scenario, SensorProfile, SimulationCommand,
+    SimulationConfig, SimulationReport, SimulationStatus, SimulationUpdate, SweepReport,
+    SweepRequest, TimeScale, TipSelection, Topology, TraceEventKind,
+};
+use crate::trace::TraceStore;
+pub use clock::deserialize_time_scale;
+use engine::{RunInputs, Simulation};
+pub use scenario::{
+    deserialize_duration_ms, deserialize_node_groups, deserialize_nodes, ScenarioFormat,
+};
+pub use sensors::{deserialize_sensor_profile, SensorGenerator};
+pub use store::RunStore;
+pub use topology::deserialize_topology;
+
+#[derive(Debug, Error)]
+pub enum SimulationError {
+    #[error("No simulation is running")]
+    NotRunning,
+    #[error(
+        "Starting {requested} virtual nodes would exceed the limit of {limit} ({running} running)"
+    )]
+    NodeLimitExceeded {
+        requested: usize,
+        running: usize,
+        limit: usize,
+    },
+    #[error("Invalid simulation config: {0}")]
+    InvalidConfig(String),
+    #[error("Invalid scenario: {0}")]
+    InvalidScenario(String),
+    #[error("Simulation run {0} not found")]
+    RunNotFound(String),
+    #[error("Trace {0} not found")]
+    TraceNotFound(String),
+    #[error("A replayed simulation cannot be changed while it runs")]
+    Replaying,
+    #[error("Failed to load simulation run: {0}")]
+    Storage(String),
+    #[error("Invalid comparison: {0}")]
+    InvalidComparison(String),
+    #[error("Invalid sweep: {0}")]
+    InvalidSweep(String),
+    #[error("Simulation sweep {0} not found")]
+    SweepNotFound(String),
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            topology: Topology::FullMesh,
+            node_count: 10,
+            block_count: 100,
+            interval_ms: 1000,
+            link: LinkModel::default(),
+            link_overrides: vec![],
+            sensor_profile: SensorProfile::default(),
+            node_sensor_profiles: vec![],
+            seed: None,
+            epoch: None,
+            time_scale: TimeScale::default(),
+            timeline: vec![],
+            adversaries: None,
+            churn: None,
+            tip_selection: TipSelection::default(),
+            parent_count: None,
+            trace: None,
+        }
+    }
+}
+
+impl SimulationConfig {
+    pub fn validate(&self) -> Result<(), SimulationError> {
+        if self.node_count == 0 {
+            return Err(SimulationError::InvalidConfig(
+                "node_count must be at least 1".to_string(),
+            ));
+        }
+        if self.block_count == 0 {
+            return Err(SimulationError::InvalidConfig(
+                "block_count must be at least 1".to_string(),
+            ));
+        }
+        if self.interval_ms == 0 {
+            return Err(SimulationError::InvalidConfig(
+                "interval_ms must be greater than 0".to_string(),
+            ));
+        }
+        if self.parent_count == Some(0) {
+            return Err(SimulationError::InvalidConfig(
+                "parent_count must be at least 1".to_string(),
+            ));
+        }
+        self.time_scale.validate()?;
+        self.link.validate()?;
+        for link in &self.link_overrides {
+            if link.a >= self.node_count || link.b >= self.node_count {
+                return Err(SimulationError::InvalidConfig(format!(
+                    "link override {}-{} is out of range for {} nodes",
+                    link.a, link.b, self.node_count
+                )));
+            }
+            link.model.validate()?;
+        }
+        self.sensor_profile.validate()?;
+        for node_profile in &self.node_sensor_profiles {
+            if let Some(&node) = node_profile
+                .nodes
+                .iter()
+                .find(|&&node| node >= self.node_count)
+            {
+                return Err(SimulationError::InvalidConfig(format!(
+                    "sensor profile node {} is out of range for {} nodes",
+                    node, self.node_count
+                )));
+            }
+            node_profile.profile.validate()?;
+        }
+        self.topology.validate(self.node_count)?;
+        for step in &self.timeline {
+            step.command.validate(self.node_count)?;
+        }
+        if let Some(adversaries) = &self.adversaries {
+            adversaries.validate()?;
+        }
+        if let Some(churn) = &self.churn {
+            churn.validate()?;
+        }
+        if self.tip_selection == TipSelection::Recorded && self.trace.is_none() {
+            return Err(SimulationError::InvalidConfig(
+                "recorded tip selection needs a trace".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Sensor profile for `node`; later per-node entries win over earlier ones.
+    pub fn sensor_profile_for(&self, node: usize) -> &SensorProfile {
+        self.node_sensor_profiles
+            .iter()
+            .rev()
+            .find(|node_profile| node_profile.nodes.contains(&node))
+            .map_or(&self.sensor_profile, |node_profile| &node_profile.profile)
+    }
+}
+
+/// Samples a standard normal value with the Box-Muller transform.
+pub(crate) fn standard_normal<R: Rng>(rng: &mut R) -> f64 {
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+/// Upper bound on virtual nodes across all running simulations.
+const DEFAULT_MAX_TOTAL_NODES: usize = 10_000;
+/// Finished runs kept in memory for status queries; older ones are still
+/// available from the run store.
+const MAX_TRACKED_RUNS: usize = 100;
+/// Sweeps kept in memory for progress queries; finished ones are still
+/// available from the run store.
+const MAX_TRACKED_SWEEPS: usize = 20;
+/// Most runs a single comparison may include.
+const MAX_COMPARED_RUNS: usize = 50;
+/// Progress updates buffered per subscriber before slow ones start missing
+/// updates.
+const UPDATE_CHANNEL_CAPACITY: usize = 1024;
+
+struct RunHandle {
+    run_id: String,
+    node_count: usize,
+    is_replay: bool,
+    status: Arc<RwLock<SimulationStatus>>,
+    commands: mpsc::UnboundedSender<SimulationCommand>,
+    stop: watch::Sender<bool>,
+    /// Set once the run's record and report have been saved.
+    finished: watch::Receiver<bool>,
+    task: JoinHandle<()>,
+}
+
+impl RunHandle {
+    fn is_running(&self) -> bool {
+        !self.task.is_finished()
+    }
+
+    fn summary(&self) -> RunSummary {
+        let status = self.status.read().unwrap();
+        RunSummary {
+            run_id: self.run_id.clone(),
+            is_running: self.is_running(),
+            scenario: status.scenario.clone(),
+            replay_of: status.replay_of.clone(),
+            node_count: self.node_count,
+            blocks_created: status.blocks_created,
+            progress: status.progress,
+            start_time: status.start_time,
+            end_time: status.end_time,
+        }
+    }
+}
+
+struct SweepHandle {
+    sweep_id: String,
+    report: Arc<RwLock<SweepReport>>,
+    stop: watch::Sender<bool>,
+}
+
+impl From<&SimulationReport> for RunSummary {
+    fn from(report: &SimulationReport) -> Self {
+        Self {
+            run_id: report.run_id.clone(),
+            is_running: false,
+            scenario: None,
+            replay_of: report.replay_of.clone(),
+            node_count: report.config.node_count,
+            blocks_created: report.blocks_created,
+            progress: if report.completed { 1.0 } else { 0.0 },
+            start_time: Some(report.start_time),
+            end_time: Some(report.end_time),
+        }
+    }
+}
+
+/// Owns the simulation config and all simulation runs started by this
+/// server, any number of which may run at once within the node limit.
+#[derive(Clone)]
+pub struct SimulationManager {
+    config: Arc<RwLock<SimulationConfig>>,
+    /// Runs in the order they were started.
+    runs: Arc<Mutex<Vec<RunHandle>>>,
+    sweeps: Arc<Mutex<Vec<SweepHandle>>>,
+    store: RunStore,
+    traces: TraceStore,
+    max_total_nodes: usize,
+    updates: broadcast::Sender<SimulationUpdate>,
+}
+
+impl Default for SimulationManager {
+    fn default() -> Self {
+        Self::with_store(RunStore::default(), DEFAULT_MAX_TOTAL_NODES)
+    }
+}
+
+impl SimulationManager {
+    /// Creates a manager that stores run artifacts under `SIMULATION_DATA_DIR`
+    /// and allows at most `SIMULATION_MAX_NODES` virtual nodes at once.
+    pub fn new() -> Self {
+        let max_total_nodes = std::env::var("SIMULATION_MAX_NODES")
+            .ok()
+            .and_then(|nodes| nodes.parse().ok())
+            .unwrap_or(DEFAULT_MAX_TOTAL_NODES);
+        Self::with_store(RunStore::from_env(), max_total_nodes)
+    }
+
+    pub fn with_store(store: RunStore, max_total_nodes: usize) -> Self {
+        Self {
+            config: Arc::new(RwLock::new(SimulationConfig::default())),
+            runs: Arc::new(Mutex::new(Vec::new())),
+            sweeps: Arc::new(Mutex::new(Vec::new())),
+            store,
+            traces: TraceStore::from_env(),
+            max_total_nodes,
+            updates: broadcast::channel(UPDATE_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    /// Most virtual nodes that may run at once across all runs.
+    pub fn max_total_nodes(&self) -> usize {
+        self.max_total_nodes
+    }
+
+    /// Live progress updates from every run.
+    pub fn subscribe(&self) -> broadcast::Receiver<SimulationUpdate> {
+        self.updates.subscribe()
+    }
+
+    pub fn config(&self) -> SimulationConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Replaces the config used by runs started without one.
+    pub fn set_config(&self, config: SimulationConfig) -> Result<(), SimulationError> {
+        config.validate()?;
+        *self.config.write().unwrap() = config;
+        Ok(())
+    }
+
+    /// Status of the most recently started run.
+    pub fn status(&self) -> SimulationStatus {
+        self.latest_run_id()
+            .and_then(|run_id| self.run_status(&run_id))
+            .unwrap_or_default()
+    }
+
+    pub fn run_status(&self, run_id: &str) -> Option<SimulationStatus> {
+        let runs = self.runs.lock().unwrap();
+        let handle = runs.iter().find(|handle| handle.run_id == run_id)?;
+        let status = handle.status.read().unwrap().clone();
+        Some(status)
+    }
+
+    pub fn latest_run_id(&self) -> Option<String> {
+        let runs = self.runs.lock().unwrap();
+        runs.last().map(|handle| handle.run_id.clone())
+    }
+
+    /// Runs started by this server plus runs with a stored report, newest
+    /// first.
+    pub fn runs(&self) -> anyhow::Result<Vec<RunSummary>> {
+        let mut summaries: Vec<RunSummary> = self
+            .runs
+            .lock()
+            .unwrap()
+            .iter()
+            .map(RunHandle::summary)
+            .collect();
+        for run_id in self.store.run_ids()? {
+            if summaries.iter().any(|summary| summary.run_id == run_id) {
+                continue;
+            }
+            if let Some(report) = self.store.load_report(&run_id)? {
+                summaries.push(RunSummary::from(&report));
+            }
+        }
+        summaries.sort_by_key(|summary| Reverse(summary.start_time));
+        Ok(summaries)
+    }
+
+    /// Starts a run with the stored config and returns its run ID.
+    pub fn start(&self) -> Result<String, SimulationError> {
+        self.launch(self.config(), RunInputs::default())
+    }
+
+    /// Starts a run with `config` and returns its run ID.
+    pub fn start_with(&self, config: SimulationConfig) -> Result<String, SimulationError> {
+        self.launch(config, RunInputs::default())
+    }
+
+    /// Starts a run of `scenario` without changing the stored config, and
+    /// returns its run ID.
+    pub fn run_scenario(&self, scenario: Scenario) -> Result<String, SimulationError> {
+        self.launch(
+            scenario.config,
+            RunInputs {
+                scenario: scenario.name,
+                ..RunInputs::default()
+            },
+        )
+    }
+
+    /// Starts a new run that reproduces the recorded run `run_id` exactly,
+    /// and returns the new run's ID.
+    pub fn replay(&self, run_id: &str) -> Result<String, SimulationError> {
+        let record = self
+            .store
+            .load_run(run_id)
+            .map_err(|e| SimulationError::Storage(e.to_string()))?
+            .ok_or_else(|| SimulationError::RunNotFound(run_id.to_string()))?;
+        self.launch(
+            record.config.clone(),
+            RunInputs {
+                replay: Some(record),
+                ..RunInputs::default()
+            },
+        )
+    }
+
+    fn launch(
+        &self,
+        mut config: SimulationConfig,
+        mut inputs: RunInputs,
+    ) -> Result<String, SimulationError> {
+        if let Some(trace_id) = &config.trace {
+            let events = self
+                .traces
+                .load(trace_id)
+                .map_err(|e| {
+                    SimulationError::InvalidConfig(format!(
+                        "failed to load trace {}: {}",
+                        trace_id, e
+                    ))
+                })?
+                .ok_or_else(|| SimulationError::TraceNotFound(trace_id.clone()))?;
+            config.block_count = events
+                .iter()
+                .filter(|event| matches!(event.kind, TraceEventKind::Block { .. }))
+                .count();
+            if config.block_count == 0 {
+                return Err(SimulationError::InvalidConfig(format!(
+                    "trace {} has no blocks",
+                    trace_id
+                )));
+            }
+            inputs.trace = events;
+        }
+        config.validate()?;
+
+        let mut runs = self.runs.lock().unwrap();
+        let running_nodes: usize = runs
+            .iter()
+            .filter(|handle| handle.is_running())
+            .map(|handle| handle.node_count)
+            .sum();
+        if config.node_count > self.max_total_nodes.saturating_sub(running_nodes) {
+            return Err(SimulationError::NodeLimitExceeded {
+                requested: config.node_count,
+                running: running_nodes,
+                limit: self.max_total_nodes,
+            });
+        }
+
+        let run_id = Uuid::new_v4().to_string();
+        let node_count = config.node_count;
+        let is_replay = inputs.replay.is_some();
+        let status = Arc::new(RwLock::new(SimulationStatus {
+            run_id: Some(run_id.clone()),
+            is_running: true,
+            node_count,
+            ..SimulationStatus::default()
+        }));
+        let (commands, commands_rx) = mpsc::unbounded_channel();
+        let (stop, stop_rx) = watch::channel(false);
+        let simulation = Simulation::new(
+            run_id.clone(),
+            config,
+            inputs,
+            self.store.clone(),
+            status.clone(),
+            self.updates.clone(),
+        );
+        let (finished_tx, finished) = watch::channel(false);
+        let task = tokio::spawn(async move {
+            simulation.run(commands_rx, stop_rx).await;
+            let _ = finished_tx.send(true);
+        });
+
+        runs.push(RunHandle {
+            run_id: run_id.clone(),
+            node_count,
+            is_replay,
+            status,
+            commands,
+            stop,
+            finished,
+            task,
+        });
+        while runs.len() > MAX_TRACKED_RUNS {
+            match runs.iter().position(|handle| !handle.is_running()) {
+                Some(index) => {
+                    runs.remove(index);
+                }
+                None => break,
+            }
+        }
+        Ok(run_id)
+    }
+
+    /// Waits until the run has finished and its report has been saved.
+    pub async fn wait(&self, run_id: &str) -> Result<(), SimulationError> {
+        let mut finished = {
+            let runs = self.runs.lock().unwrap();
+            runs.iter()
+                .find(|handle| handle.run_id == run_id)
+                .ok_or_else(|| SimulationError::RunNotFound(run_id.to_string()))?
+                .finished
+                .clone()
+        };
+        while !*finished.borrow_and_update() {
+            // The sender is dropped without sending if the run panicked.
+            if finished.changed().await.is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Cuts the run's network into the given node groups.
+    pub fn partition(&self, run_id: &str, groups: Vec<Vec<usize>>) -> Result<(), SimulationError> {
+        self.send(run_id, SimulationCommand::Partition { groups })
+    }
+
+    /// Removes any partition from the run.
+    pub fn heal(&self, run_id: &str) -> Result<(), SimulationError> {
+        self.send(run_id, SimulationCommand::Heal)
+    }
+
+    fn send(&self, run_id: &str, command: SimulationCommand) -> Result<(), SimulationError> {
+        let runs = self.runs.lock().unwrap();
+        let handle = Self::running(&runs, run_id)?;
+        if handle.is_replay {
+            return Err(SimulationError::Replaying);
+        }
+        command.validate(handle.node_count)?;
+        handle
+            .commands
+            .send(command)
+            .map_err(|_| SimulationError::NotRunning)
+    }
+
+    pub fn stop(&self, run_id: &str) -> Result<(), SimulationError> {
+        let runs = self.runs.lock().unwrap();
+        let handle = Self::running(&runs, run_id)?;
+        let _ = handle.stop.send(true);
+        Ok(())
+    }
+
+    fn running<'a>(runs: &'a [RunHandle], run_id: &str) -> Result<&'a RunHandle, SimulationError> {
+        let handle = runs
+            .iter()
+            .find(|handle| handle.run_id == run_id)
+            .ok_or_else(|| SimulationError::RunNotFound(run_id.to_string()))?;
+        if !handle.is_running() {
+            return Err(SimulationError::NotRunning);
+        }
+        Ok(handle)
+    }
+
+    /// Report of a finished run, or `None` if no report exists for `run_id`.
+    pub fn report(&self, run_id: &str) -> anyhow::Result<Option<SimulationReport>> {
+        self.store.load_report(run_id)
+    }
+
+    /// Starts running every combination of the request's parameter values
+    /// and returns the sweep's ID.
+    pub fn sweep(&self, mut request: SweepRequest) -> Result<String, SimulationError> {
+        let parallelism = request.parallelism.unwrap_or(1);
+        if parallelism == 0 {
+            return Err(SimulationError::InvalidSweep(
+                "parallelism must be at least 1".to_string(),
+            ));
+        }
+        // Combinations differ only in the swept parameters.
+        request.base.seed.get_or_insert_with(rand::random);
+        let combinations = request.combinations()?;
+        for combination in &combinations {
+            combination.config(&request.base).validate()?;
+        }
+        let largest = combinations
+            .iter()
+            .map(|combination| combination.node_count)
+            .max()
+            .unwrap_or(0);
+        let at_once = parallelism.min(combinations.len());
+        if largest.saturating_mul(at_once) > self.max_total_nodes {
+            return Err(SimulationError::InvalidSweep(format!(
+                "running {} combinations of up to {} nodes at once would exceed the limit of {} nodes",
+                at_once, largest, self.max_total_nodes
+            )));
+        }
+
+        let sweep_id = Uuid::new_v4().to_string();
+        let report = Arc::new(RwLock::new(SweepReport {
+            sweep_id: sweep_id.clone(),
+            base: request.base,
+            parameters: request.parameters,
+            parallelism,
+            is_running: true,
+            start_time: Utc::now(),
+            end_time: None,
+            finished_runs: 0,
+            combinations,
+            summary: vec![],
+        }));
+        let (stop, stop_rx) = watch::channel(false);
+        tokio::spawn(sweep::run_sweep(self.clone(), report.clone(), stop_rx));
+
+        let mut sweeps = self.sweeps.lock().unwrap();
+        sweeps.push(SweepHandle {
+            sweep_id: sweep_id.clone(),
+            report,
+            stop,
+        });
+        while sweeps.len() > MAX_TRACKED_SWEEPS {
+            match sweeps
+                .iter()
+                .position(|handle| !handle.report.read().unwrap().is_running)
+            {
+                Some(index) => {
+                    sweeps.remove(index);
+                }
+                None => break,
+            }
+        }
+        Ok(sweep_id)
+    }
+
+    /// Report of a sweep, live while it runs, or `None` if there is no such
+    /// sweep.
+    pub fn sweep_report(&self, sweep_id: &str) -> anyhow::Result<Option<SweepReport>> {
+        let sweeps = self.sweeps.lock().unwrap();
+        match sweeps.iter().find(|handle| handle.sweep_id == sweep_id) {
+            Some(handle) => Ok(Some(handle.report.read().unwrap().clone())),
+            None => self.store.load_sweep(sweep_id),
+        }
+    }
+
+    /// Stops a sweep's running combinations and skips the rest.
+    pub fn stop_sweep(&self, sweep_id: &str) -> Result<(), SimulationError> {
+        let running: Vec<String> = {
+            let sweeps = self.sweeps.lock().unwrap();
+            let handle = sweeps
+                .iter()
+                .find(|handle| handle.sweep_id == sweep_id)
+                .ok_or_else(|| SimulationError::SweepNotFound(sweep_id.to_string()))?;
+            let report = handle.report.read().unwrap();
+            if !report.is_running {
+                return Err(SimulationError::NotRunning);
+            }
+            let _ = handle.stop.send(true);
+            report
+                .combinations
+                .iter()
+                .filter(|combination| combination.metrics.is_none() && combination.error.is_none())
+                .filter_map(|combination| combination.run_id.clone())
+                .collect()
+        };
+        for run_id in running {
+            // The run may finish on its own in the meantime.
+            let _ = self.stop(&run_id);
+        }
+        Ok(())
+    }
+
+    /// Compares the reports of finished runs side by side, against the
+    /// first run given.
+    pub fn compare(&self, run_ids: &[String]) -> Result<RunComparison, SimulationError> {
+        if run_ids.len() < 2 || run_ids.len() > MAX_COMPARED_RUNS {
+            return Err(SimulationError::InvalidComparison(format!(
+                "give between 2 and {} run IDs",
+                MAX_COMPARED_RUNS
+            )));
+        }
+        let reports = run_ids
+            .iter()
+            .map(|run_id| {
+                self.store
+                    .load_report(run_id)
+                    .map_err(|e| SimulationError::Storage(e.to_string()))?
+                    .ok_or_else(|| SimulationError::RunNotFound(run_id.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(RunComparison::new(&reports))
+    }
+
+    /// JSON Lines event log of a finished run.
+    pub fn events(&self, run_id: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        self.store.load_events(run_id)
+    }
+}
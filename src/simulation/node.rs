@@ -0,0 +1,98 @@
+use rand::{seq::index::sample, Rng};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use super::engine::SimBlock;
+
+/// Number of tips a virtual node approves when it creates a block unless the
+/// config says otherwise, matching the local node's tip selection in
+/// `create_block`.
+pub(crate) const DEFAULT_PARENT_COUNT: usize = 2;
+
+/// An in-process node holding its own view of the simulated tangle.
+pub(crate) struct VirtualNode {
+    blocks: BTreeMap<u64, Arc<SimBlock>>,
+    tips: BTreeSet<u64>,
+    /// Parents referenced by received blocks that this node does not have.
+    missing: BTreeSet<u64>,
+}
+
+impl VirtualNode {
+    pub fn new() -> Self {
+        Self {
+            blocks: BTreeMap::new(),
+            tips: BTreeSet::new(),
+            missing: BTreeSet::new(),
+        }
+    }
+
+    /// Picks up to `count` of the most recent tips as parents for a new
+    /// block.
+    pub fn select_parents(&self, count: usize) -> Vec<u64> {
+        let mut tips: Vec<&Arc<SimBlock>> = self.tips.iter().map(|id| &self.blocks[id]).collect();
+        tips.sort_by(|a, b| b.created_at_ms.cmp(&a.created_at_ms).then(b.id.cmp(&a.id)));
+        tips.into_iter().take(count).map(|block| block.id).collect()
+    }
+
+    /// Picks up to `count` tips at random as parents.
+    pub fn select_random<R: Rng>(&self, count: usize, rng: &mut R) -> Vec<u64> {
+        let tips: Vec<u64> = self.tips.iter().copied().collect();
+        sample(rng, tips.len(), tips.len().min(count))
+            .into_iter()
+            .map(|index| tips[index])
+            .collect()
+    }
+
+    /// Picks up to `count` of the oldest known blocks as parents, leaving the
+    /// tips unapproved.
+    pub fn select_oldest(&self, count: usize) -> Vec<u64> {
+        let mut blocks: Vec<&Arc<SimBlock>> = self.blocks.values().collect();
+        blocks.sort_by(|a, b| a.created_at_ms.cmp(&b.created_at_ms).then(a.id.cmp(&b.id)));
+        blocks
+            .into_iter()
+            .take(count)
+            .map(|block| block.id)
+            .collect()
+    }
+
+    pub fn tip_count(&self) -> usize {
+        self.tips.len()
+    }
+
+    pub fn has(&self, block: u64) -> bool {
+        self.blocks.contains_key(&block)
+    }
+
+    pub fn missing_count(&self) -> usize {
+        self.missing.len()
+    }
+
+    /// Blocks this node has that `other` lacks, oldest first.
+    pub fn blocks_missing_from(&self, other: &VirtualNode) -> Vec<Arc<SimBlock>> {
+        self.blocks
+            .values()
+            .filter(|block| !other.has(block.id))
+            .cloned()
+            .collect()
+    }
+
+    /// Adds a block to this node's tangle. Returns `false` if the node already
+    /// had it.
+    pub fn receive(&mut self, block: Arc<SimBlock>) -> bool {
+        if self.blocks.contains_key(&block.id) {
+            return false;
+        }
+        for parent in &block.parents {
+            self.tips.remove(parent);
+            if !self.blocks.contains_key(parent) {
+                self.missing.insert(*parent);
+            }
+        }
+        self.missing.remove(&block.id);
+        self.tips.insert(block.id);
+        self.blocks.insert(block.id, block);
+        true
+    }
+}
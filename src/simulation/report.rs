@@ -0,0 +1,489 @@
+use chrono::Utc;
+use std::{collections::BTreeSet, fmt::Write as _};
+
+use super::adversary::Adversaries;
+use super::engine::SimBlock;
+use crate::models::{
+    AdversaryReport, ChurnReport, ConfirmationReport, HistogramBucket, LatencySummary, NodeReport,
+    PropagationReport, ReportTable, RunRecord, SimulationReport, SimulationStatus, TipSample,
+};
+use crate::stats::percentile;
+
+/// Lower bounds of the propagation delay histogram buckets, in milliseconds.
+const HISTOGRAM_BOUNDS_MS: [u64; 11] = [0, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// Most tip count samples kept in a report. Once reached, every other sample
+/// is dropped and the sampling stride doubles, so long runs keep an evenly
+/// spaced series instead of one sample per block.
+const MAX_TIP_SAMPLES: usize = 1000;
+
+/// Collects per-block and per-node observations while a simulation runs and
+/// turns them into a `SimulationReport` at the end.
+///
+/// Block IDs of published blocks are assigned sequentially by the engine, so
+/// per-block state is kept in vectors indexed by block ID.
+///
+/// Alongside the network-wide metrics it tracks the same metrics restricted
+/// to honest blocks and honest nodes, to show the effect of adversaries.
+pub(crate) struct ReportRecorder {
+    node_count: usize,
+    adversarial: Vec<bool>,
+    honest_nodes: usize,
+    created_at: Vec<u64>,
+    reached: Vec<usize>,
+    confirmed_after: Vec<Option<u64>>,
+    propagation_delays: Vec<u64>,
+    full_propagation: Vec<u64>,
+    honest_blocks: Vec<bool>,
+    honest_reached: Vec<usize>,
+    honest_delays: Vec<u64>,
+    honest_full_propagation: Vec<u64>,
+    tips: BTreeSet<u64>,
+    tip_counts: Vec<TipSample>,
+    tip_stride: u64,
+    tip_calls: u64,
+    honest_tip_sum: f64,
+    honest_tip_samples: u64,
+    nodes: Vec<NodeReport>,
+}
+
+impl ReportRecorder {
+    /// `adversarial` flags each node that misbehaves during the run.
+    pub fn new(adversarial: &[bool]) -> Self {
+        Self {
+            node_count: adversarial.len(),
+            adversarial: adversarial.to_vec(),
+            honest_nodes: adversarial.iter().filter(|&&flag| !flag).count(),
+            created_at: Vec::new(),
+            reached: Vec::new(),
+            confirmed_after: Vec::new(),
+            propagation_delays: Vec::new(),
+            full_propagation: Vec::new(),
+            honest_blocks: Vec::new(),
+            honest_reached: Vec::new(),
+            honest_delays: Vec::new(),
+            honest_full_propagation: Vec::new(),
+            tips: BTreeSet::new(),
+            tip_counts: Vec::new(),
+            tip_stride: 1,
+            tip_calls: 0,
+            honest_tip_sum: 0.0,
+            honest_tip_samples: 0,
+            nodes: adversarial
+                .iter()
+                .enumerate()
+                .map(|(node, &adversarial)| NodeReport {
+                    node,
+                    adversarial,
+                    ..NodeReport::default()
+                })
+                .collect(),
+        }
+    }
+
+    pub fn block_created(&mut self, origin: usize, block: &SimBlock) {
+        for &parent in &block.parents {
+            // Withheld parents were never published and are not tracked.
+            if let Some(confirmed) = self.confirmed_after.get_mut(parent as usize) {
+                if confirmed.is_none() {
+                    *confirmed = Some(block.created_at_ms - self.created_at[parent as usize]);
+                }
+            }
+            self.tips.remove(&parent);
+        }
+        self.tips.insert(block.id);
+
+        let honest = !self.adversarial[origin];
+        self.created_at.push(block.created_at_ms);
+        self.reached.push(1);
+        self.confirmed_after.push(None);
+        self.honest_blocks.push(honest);
+        self.honest_reached.push(usize::from(honest));
+        if self.node_count == 1 {
+            self.full_propagation.push(0);
+        }
+        if honest && self.honest_nodes == 1 {
+            self.honest_full_propagation.push(0);
+        }
+        self.nodes[origin].blocks_created += 1;
+    }
+
+    /// `node`, other than the origin, received `block` for the first time.
+    /// Returns `true` once the block has reached every node.
+    pub fn block_reached(&mut self, block: &SimBlock, node: usize, now_ms: u64) -> bool {
+        let delay = now_ms - block.created_at_ms;
+        self.propagation_delays.push(delay);
+
+        let id = block.id as usize;
+        if self.honest_blocks[id] && !self.adversarial[node] {
+            self.honest_delays.push(delay);
+            self.honest_reached[id] += 1;
+            if self.honest_reached[id] == self.honest_nodes {
+                self.honest_full_propagation.push(delay);
+            }
+        }
+
+        self.reached[id] += 1;
+        if self.reached[id] == self.node_count {
+            self.full_propagation.push(delay);
+            return true;
+        }
+        false
+    }
+
+    pub fn message_sent(&mut self, node: usize) {
+        self.nodes[node].messages_sent += 1;
+    }
+
+    pub fn message_received(&mut self, node: usize, duplicate: bool) {
+        self.nodes[node].messages_received += 1;
+        if duplicate {
+            self.nodes[node].duplicate_blocks += 1;
+        }
+    }
+
+    pub fn sample_tips(&mut self, now_ms: u64, mean_node_tips: f64, mean_honest_tips: f64) {
+        self.honest_tip_sum += mean_honest_tips;
+        self.honest_tip_samples += 1;
+
+        let call = self.tip_calls;
+        self.tip_calls += 1;
+        if !call.is_multiple_of(self.tip_stride) {
+            return;
+        }
+        self.tip_counts.push(TipSample {
+            virtual_time_ms: now_ms,
+            tips: self.tips.len(),
+            mean_node_tips,
+        });
+        if self.tip_counts.len() == MAX_TIP_SAMPLES {
+            let mut index = 0;
+            self.tip_counts.retain(|_| {
+                index += 1;
+                index % 2 == 1
+            });
+            self.tip_stride *= 2;
+        }
+    }
+
+    /// Builds the report from the recorded observations, the run record, the
+    /// run's final status and, if the run had any, its adversaries and churn.
+    pub fn finish(
+        self,
+        record: &RunRecord,
+        status: &SimulationStatus,
+        completed: bool,
+        adversaries: &Adversaries,
+        churn: Option<ChurnReport>,
+    ) -> SimulationReport {
+        let adversaries = record.config.adversaries.is_some().then(|| {
+            let honest_blocks = self.honest_blocks.iter().filter(|&&honest| honest).count();
+            let honest_confirmations = self
+                .confirmed_after
+                .iter()
+                .zip(&self.honest_blocks)
+                .filter(|(_, &honest)| honest)
+                .filter_map(|(confirmed, _)| *confirmed)
+                .collect();
+            let tally = &adversaries.tally;
+            AdversaryReport {
+                nodes: adversaries.nodes(),
+                blocks_created: self.created_at.len() - honest_blocks,
+                spam_blocks: tally.spam_blocks,
+                conflicting_blocks: tally.conflicting_blocks,
+                duplicate_messages: tally.duplicate_messages,
+                rejected_blocks: tally.rejected_blocks,
+                withheld_parents: tally.withheld_parents,
+                honest_nodes_with_conflicts: tally.honest_nodes_with_conflicts,
+                unresolved_parents: tally.unresolved_parents,
+                honest_propagation: PropagationReport {
+                    delay: summarize(self.honest_delays.clone()),
+                    histogram: histogram(&self.honest_delays),
+                    incomplete_blocks: honest_blocks - self.honest_full_propagation.len(),
+                    full_propagation: summarize(self.honest_full_propagation.clone()),
+                },
+                honest_confirmation: confirmation_report(honest_confirmations, honest_blocks),
+                mean_honest_tips: if self.honest_tip_samples == 0 {
+                    0.0
+                } else {
+                    self.honest_tip_sum / self.honest_tip_samples as f64
+                },
+            }
+        });
+
+        let blocks = self.created_at.len();
+        let confirmations: Vec<u64> = self.confirmed_after.iter().flatten().copied().collect();
+
+        SimulationReport {
+            run_id: record.run_id.clone(),
+            replay_of: record.replay_of.clone(),
+            config: record.config.clone(),
+            event_log_digest: record.event_log_digest.clone(),
+            completed,
+            start_time: status.start_time.unwrap_or_else(Utc::now),
+            end_time: status.end_time.unwrap_or_else(Utc::now),
+            virtual_duration_ms: status.virtual_time_ms,
+            blocks_created: status.blocks_created,
+            blocks_sent: status.blocks_sent,
+            blocks_received: status.blocks_received,
+            duplicate_blocks: status.duplicate_blocks,
+            messages_lost: status.messages_lost,
+            messages_partitioned: status.messages_partitioned,
+            messages_offline: status.messages_offline,
+            propagation: PropagationReport {
+                delay: summarize(self.propagation_delays.clone()),
+                histogram: histogram(&self.propagation_delays),
+                incomplete_blocks: blocks - self.full_propagation.len(),
+                full_propagation: summarize(self.full_propagation),
+            },
+            confirmation: confirmation_report(confirmations, blocks),
+            tip_counts: self.tip_counts,
+            nodes: self.nodes,
+            adversaries,
+            churn,
+        }
+    }
+}
+
+fn confirmation_report(confirmations: Vec<u64>, blocks: usize) -> ConfirmationReport {
+    let unconfirmed_blocks = blocks - confirmations.len();
+    ConfirmationReport {
+        latency: summarize(confirmations),
+        unconfirmed_blocks,
+        orphan_rate: if blocks == 0 {
+            0.0
+        } else {
+            unconfirmed_blocks as f64 / blocks as f64
+        },
+    }
+}
+
+pub(crate) fn summarize(mut samples: Vec<u64>) -> LatencySummary {
+    if samples.is_empty() {
+        return LatencySummary::default();
+    }
+    samples.sort_unstable();
+    LatencySummary {
+        samples: samples.len(),
+        mean_ms: samples.iter().map(|&ms| ms as f64).sum::<f64>() / samples.len() as f64,
+        p50_ms: percentile(&samples, 50.0),
+        p90_ms: percentile(&samples, 90.0),
+        p99_ms: percentile(&samples, 99.0),
+        max_ms: samples[samples.len() - 1],
+    }
+}
+
+fn histogram(delays: &[u64]) -> Vec<HistogramBucket> {
+    let mut buckets: Vec<HistogramBucket> = HISTOGRAM_BOUNDS_MS
+        .iter()
+        .enumerate()
+        .map(|(i, &min_ms)| HistogramBucket {
+            min_ms,
+            max_ms: HISTOGRAM_BOUNDS_MS.get(i + 1).copied(),
+            count: 0,
+        })
+        .collect();
+    for &delay in delays {
+        let index = HISTOGRAM_BOUNDS_MS.partition_point(|&bound| bound <= delay) - 1;
+        buckets[index].count += 1;
+    }
+    buckets
+}
+
+impl SimulationReport {
+    /// Renders one section of the report as CSV with a header row.
+    pub fn to_csv(&self, table: ReportTable) -> String {
+        let mut csv = String::new();
+        match table {
+            ReportTable::Summary => {
+                let propagation = &self.propagation;
+                let confirmation = &self.confirmation;
+                csv.push_str("metric,value\n");
+                let rows = [
+                    ("run_id", self.run_id.clone()),
+                    ("completed", self.completed.to_string()),
+                    ("start_time", self.start_time.to_rfc3339()),
+                    ("end_time", self.end_time.to_rfc3339()),
+                    ("virtual_duration_ms", self.virtual_duration_ms.to_string()),
+                    ("node_count", self.config.node_count.to_string()),
+                    ("blocks_created", self.blocks_created.to_string()),
+                    ("blocks_sent", self.blocks_sent.to_string()),
+                    ("blocks_received", self.blocks_received.to_string()),
+                    ("duplicate_blocks", self.duplicate_blocks.to_string()),
+                    ("messages_lost", self.messages_lost.to_string()),
+                    (
+                        "messages_partitioned",
+                        self.messages_partitioned.to_string(),
+                    ),
+                    ("messages_offline", self.messages_offline.to_string()),
+                    ("propagation_mean_ms", propagation.delay.mean_ms.to_string()),
+                    ("propagation_p50_ms", propagation.delay.p50_ms.to_string()),
+                    ("propagation_p99_ms", propagation.delay.p99_ms.to_string()),
+                    (
+                        "full_propagation_mean_ms",
+                        propagation.full_propagation.mean_ms.to_string(),
+                    ),
+                    (
+                        "incomplete_blocks",
+                        propagation.incomplete_blocks.to_string(),
+                    ),
+                    (
+                        "confirmation_mean_ms",
+                        confirmation.latency.mean_ms.to_string(),
+                    ),
+                    (
+                        "confirmation_p50_ms",
+                        confirmation.latency.p50_ms.to_string(),
+                    ),
+                    (
+                        "confirmation_p99_ms",
+                        confirmation.latency.p99_ms.to_string(),
+                    ),
+                    (
+                        "unconfirmed_blocks",
+                        confirmation.unconfirmed_blocks.to_string(),
+                    ),
+                    ("orphan_rate", confirmation.orphan_rate.to_string()),
+                ];
+                for (metric, value) in rows {
+                    let _ = writeln!(csv, "{},{}", metric, value);
+                }
+                if let Some(churn) = &self.churn {
+                    let rows = [
+                        ("departures", churn.departures.to_string()),
+                        ("rejoins", churn.rejoins.to_string()),
+                        ("outages", churn.outages.to_string()),
+                        ("availability", churn.availability.to_string()),
+                        ("resyncs", churn.resyncs.to_string()),
+                        ("incomplete_resyncs", churn.incomplete_resyncs.to_string()),
+                        ("missed_blocks", churn.missed_blocks.to_string()),
+                        ("resynced_blocks", churn.resynced_blocks.to_string()),
+                        ("resync_mean_ms", churn.resync_time.mean_ms.to_string()),
+                        ("resync_p99_ms", churn.resync_time.p99_ms.to_string()),
+                    ];
+                    for (metric, value) in rows {
+                        let _ = writeln!(csv, "{},{}", metric, value);
+                    }
+                }
+                if let Some(adversaries) = &self.adversaries {
+                    let rows = [
+                        ("adversarial_nodes", adversaries.nodes.len().to_string()),
+                        (
+                            "adversary_blocks_created",
+                            adversaries.blocks_created.to_string(),
+                        ),
+                        ("rejected_blocks", adversaries.rejected_blocks.to_string()),
+                        (
+                            "honest_nodes_with_conflicts",
+                            adversaries.honest_nodes_with_conflicts.to_string(),
+                        ),
+                        (
+                            "unresolved_parents",
+                            adversaries.unresolved_parents.to_string(),
+                        ),
+                        (
+                            "honest_full_propagation_mean_ms",
+                            adversaries
+                                .honest_propagation
+                                .full_propagation
+                                .mean_ms
+                                .to_string(),
+                        ),
+                        (
+                            "honest_confirmation_mean_ms",
+                            adversaries.honest_confirmation.latency.mean_ms.to_string(),
+                        ),
+                        (
+                            "honest_orphan_rate",
+                            adversaries.honest_confirmation.orphan_rate.to_string(),
+                        ),
+                        ("mean_honest_tips", adversaries.mean_honest_tips.to_string()),
+                    ];
+                    for (metric, value) in rows {
+                        let _ = writeln!(csv, "{},{}", metric, value);
+                    }
+                }
+            }
+            ReportTable::PropagationHistogram => {
+                csv.push_str("min_ms,max_ms,count\n");
+                for bucket in &self.propagation.histogram {
+                    let max_ms = bucket.max_ms.map(|max| max.to_string()).unwrap_or_default();
+                    let _ = writeln!(csv, "{},{},{}", bucket.min_ms, max_ms, bucket.count);
+                }
+            }
+            ReportTable::TipCounts => {
+                csv.push_str("virtual_time_ms,tips,mean_node_tips\n");
+                for sample in &self.tip_counts {
+                    let _ = writeln!(
+                        csv,
+                        "{},{},{}",
+                        sample.virtual_time_ms, sample.tips, sample.mean_node_tips
+                    );
+                }
+            }
+            ReportTable::Nodes => {
+                csv.push_str(
+                    "node,adversarial,blocks_created,messages_sent,messages_received,duplicate_blocks\n",
+                );
+                for node in &self.nodes {
+                    let _ = writeln!(
+                        csv,
+                        "{},{},{},{},{},{}",
+                        node.node,
+                        node.adversarial,
+                        node.blocks_created,
+                        node.messages_sent,
+                        node.messages_received,
+                        node.duplicate_blocks
+                    );
+                }
+            }
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_by_lower_bound() {
+        let buckets = histogram(&[0, 9, 10, 99, 100, 10000, 60000]);
+        let counts: Vec<usize> = buckets.iter().map(|bucket| bucket.count).collect();
+        assert_eq!(counts, [2, 1, 0, 1, 1, 0, 0, 0, 0, 0, 2]);
+        assert_eq!(buckets[1].min_ms, 10);
+        assert_eq!(buckets[1].max_ms, Some(25));
+        assert_eq!(buckets[10].max_ms, None);
+    }
+
+    #[test]
+    fn summarize_sorts_samples() {
+        let summary = summarize(vec![30, 10, 20, 40]);
+        assert_eq!(summary.samples, 4);
+        assert_eq!(summary.mean_ms, 25.0);
+        assert_eq!(summary.p50_ms, 20);
+        assert_eq!(summary.p99_ms, 40);
+        assert_eq!(summary.max_ms, 40);
+        assert_eq!(summarize(Vec::new()).samples, 0);
+    }
+
+    #[test]
+    fn tip_counts_stay_bounded_and_evenly_spaced() {
+        let mut recorder = ReportRecorder::new(&[false, false]);
+        for now_ms in 0..100_000 {
+            recorder.sample_tips(now_ms, 1.0, 2.0);
+        }
+        let samples = &recorder.tip_counts;
+        assert!(samples.len() < MAX_TIP_SAMPLES);
+        assert!(samples.len() >= MAX_TIP_SAMPLES / 2);
+        let stride = recorder.tip_stride;
+        for (i, sample) in samples.iter().enumerate() {
+            assert_eq!(sample.virtual_time_ms, i as u64 * stride);
+        }
+        assert_eq!(
+            recorder.honest_tip_sum / recorder.honest_tip_samples as f64,
+            2.0
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use serde::{de::Error as _, Deserialize, Deserializer};
+
+use super::link::validate_partition;
+use super::SimulationError;
+use crate::models::{Scenario, SimulationCommand};
+
+/// Serialization format of a scenario document.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScenarioFormat {
+    Json,
+    Toml,
+}
+
+impl ScenarioFormat {
+    /// Picks the format from a request's content type, defaulting to JSON.
+    pub fn from_content_type(content_type: Option<&str>) -> Self {
+        match content_type {
+            Some(content_type) if content_type.contains("toml") => ScenarioFormat::Toml,
+            _ => ScenarioFormat::Json,
+        }
+    }
+}
+
+impl Scenario {
+    /// Parses and validates a scenario document.
+    pub fn parse(document: &str, format: ScenarioFormat) -> Result<Self, SimulationError> {
+        let scenario: Scenario = match format {
+            ScenarioFormat::Json => serde_json::from_str(document)
+                .map_err(|e| SimulationError::InvalidScenario(e.to_string()))?,
+            ScenarioFormat::Toml => toml::from_str(document)
+                .map_err(|e| SimulationError::InvalidScenario(e.to_string()))?,
+        };
+        scenario.config.validate()?;
+        Ok(scenario)
+    }
+}
+
+impl SimulationCommand {
+    pub fn validate(&self, node_count: usize) -> Result<(), SimulationError> {
+        match self {
+            SimulationCommand::Partition { groups } => validate_partition(node_count, groups),
+            SimulationCommand::Heal => Ok(()),
+            SimulationCommand::NodeLeave { nodes } | SimulationCommand::NodeJoin { nodes } => {
+                match nodes.iter().find(|&&node| node >= node_count) {
+                    Some(node) => Err(SimulationError::InvalidConfig(format!(
+                        "node {} is out of range for {} nodes",
+                        node, node_count
+                    ))),
+                    None => Ok(()),
+                }
+            }
+            SimulationCommand::BlockRate { factor } if !(factor.is_finite() && *factor > 0.0) => {
+                Err(SimulationError::InvalidConfig(
+                    "block_rate factor must be a positive number".to_string(),
+                ))
+            }
+            SimulationCommand::BlockRate { .. } => Ok(()),
+        }
+    }
+}
+
+/// Most nodes one request's node lists may name in total. Ranges are checked
+/// against it before they are expanded, since the real node count is only
+/// known once the lists are validated.
+const MAX_LISTED_NODES: usize = 1 << 20;
+
+/// Appends `start..=end` to `nodes` unless that would exceed `limit` nodes.
+fn extend_nodes(
+    nodes: &mut Vec<usize>,
+    start: usize,
+    end: usize,
+    limit: usize,
+) -> Result<(), String> {
+    if end - start >= limit.saturating_sub(nodes.len()) {
+        return Err(format!(
+            "node lists must not name more than {} nodes",
+            MAX_LISTED_NODES
+        ));
+    }
+    nodes.extend(start..=end);
+    Ok(())
+}
+
+/// Parses node lists such as `"0-4"` or `"0-4,7"` into `nodes`.
+fn parse_nodes(spec: &str, nodes: &mut Vec<usize>, limit: usize) -> Result<(), String> {
+    for part in spec
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        let parse = |value: &str| {
+            value
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| format!("invalid node '{}'", value.trim()))
+        };
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse(start)?, parse(end)?);
+                if start > end {
+                    return Err(format!("invalid node range '{}'", part));
+                }
+                extend_nodes(nodes, start, end, limit)?;
+            }
+            None => {
+                let node = parse(part)?;
+                extend_nodes(nodes, node, node, limit)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NodeSpec {
+    Index(usize),
+    Spec(String),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NodesInput {
+    Spec(String),
+    List(Vec<NodeSpec>),
+}
+
+impl NodesInput {
+    /// Expands the list, failing if it names more than `limit` nodes.
+    fn into_nodes(self, limit: usize) -> Result<Vec<usize>, String> {
+        let mut nodes = Vec::new();
+        match self {
+            NodesInput::Spec(spec) => parse_nodes(&spec, &mut nodes, limit)?,
+            NodesInput::List(specs) => {
+                for spec in specs {
+                    match spec {
+                        NodeSpec::Index(node) => extend_nodes(&mut nodes, node, node, limit)?,
+                        NodeSpec::Spec(spec) => parse_nodes(&spec, &mut nodes, limit)?,
+                    }
+                }
+            }
+        }
+        Ok(nodes)
+    }
+}
+
+/// Accepts a node list as indices, range strings like `"0-4"`, or a mix.
+pub fn deserialize_nodes<'de, D>(deserializer: D) -> Result<Vec<usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    NodesInput::deserialize(deserializer)?
+        .into_nodes(MAX_LISTED_NODES)
+        .map_err(D::Error::custom)
+}
+
+/// Accepts partition groups where each group is a node list as understood by
+/// `deserialize_nodes`.
+pub fn deserialize_node_groups<'de, D>(deserializer: D) -> Result<Vec<Vec<usize>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut listed = 0;
+    Vec::<NodesInput>::deserialize(deserializer)?
+        .into_iter()
+        .map(|group| {
+            let nodes = group.into_nodes(MAX_LISTED_NODES - listed)?;
+            listed += nodes.len();
+            Ok(nodes)
+        })
+        .collect::<Result<_, String>>()
+        .map_err(D::Error::custom)
+}
+
+/// Accepts a duration as milliseconds or as a string with a unit: `"250ms"`,
+/// `"30s"`, `"2m"` or `"1h"`.
+pub fn deserialize_duration_ms<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum DurationInput {
+        Millis(u64),
+        Text(String),
+    }
+
+    let text = match DurationInput::deserialize(deserializer)? {
+        DurationInput::Millis(ms) => return Ok(ms),
+        DurationInput::Text(text) => text,
+    };
+    let trimmed = text.trim();
+    let split = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (value, unit) = trimmed.split_at(split);
+    let value: f64 = value
+        .parse()
+        .map_err(|_| D::Error::custom(format!("invalid duration '{}'", text)))?;
+    let scale = match unit.trim() {
+        "ms" => 1.0,
+        "s" => 1_000.0,
+        "m" => 60_000.0,
+        "h" => 3_600_000.0,
+        _ => {
+            return Err(D::Error::custom(format!(
+                "invalid duration unit in '{}', use ms, s, m or h",
+                text
+            )))
+        }
+    };
+    Ok((value * scale).round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn duration(value: serde_json::Value) -> Result<u64, String> {
+        deserialize_duration_ms(value).map_err(|e| e.to_string())
+    }
+
+    fn nodes(value: serde_json::Value) -> Result<Vec<usize>, String> {
+        deserialize_nodes(value).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn durations_accept_millis_and_units() {
+        assert_eq!(duration(json!(1500)), Ok(1500));
+        assert_eq!(duration(json!("250ms")), Ok(250));
+        assert_eq!(duration(json!(" 30s ")), Ok(30_000));
+        assert_eq!(duration(json!("1.5s")), Ok(1500));
+        assert_eq!(duration(json!("2m")), Ok(120_000));
+        assert_eq!(duration(json!("1 h")), Ok(3_600_000));
+    }
+
+    #[test]
+    fn durations_reject_bad_values_and_units() {
+        assert!(duration(json!("30")).unwrap_err().contains("unit"));
+        assert!(duration(json!("2d")).unwrap_err().contains("unit"));
+        assert!(duration(json!("s"))
+            .unwrap_err()
+            .contains("invalid duration"));
+        assert!(duration(json!("-5s")).is_err());
+    }
+
+    #[test]
+    fn node_lists_mix_indices_and_ranges() {
+        assert_eq!(nodes(json!("0-2, 5")), Ok(vec![0, 1, 2, 5]));
+        assert_eq!(nodes(json!([7, "1-2", "4"])), Ok(vec![7, 1, 2, 4]));
+        assert_eq!(nodes(json!("")), Ok(vec![]));
+        assert!(nodes(json!("3-1"))
+            .unwrap_err()
+            .contains("invalid node range"));
+        assert!(nodes(json!("a-2"))
+            .unwrap_err()
+            .contains("invalid node 'a'"));
+    }
+
+    #[test]
+    fn node_lists_are_capped_before_expanding() {
+        let huge = format!("0-{}", usize::MAX);
+        assert!(nodes(json!(huge))
+            .unwrap_err()
+            .contains("must not name more"));
+        let limit = format!("0-{}", MAX_LISTED_NODES - 1);
+        assert_eq!(nodes(json!(limit)).unwrap().len(), MAX_LISTED_NODES);
+
+        let groups = json!([format!("0-{}", MAX_LISTED_NODES - 1), "0"]);
+        assert!(deserialize_node_groups(groups).is_err());
+    }
+}
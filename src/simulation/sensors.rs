@@ -0,0 +1,333 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{de::Error as _, Deserialize, Deserializer};
+use std::f64::consts::PI;
+
+use super::{standard_normal, SimulationError};
+use crate::models::{DiurnalCycle, PollutionSpikes, RandomWalk, SensorFault, SensorProfile};
+use ecoblock_core::SensorData;
+
+const MS_PER_HOUR: f64 = 3_600_000.0;
+
+impl Default for DiurnalCycle {
+    fn default() -> Self {
+        Self {
+            mean: 18.0,
+            amplitude: 6.0,
+            peak_hour: 15.0,
+            noise: 0.3,
+        }
+    }
+}
+
+impl Default for RandomWalk {
+    fn default() -> Self {
+        Self {
+            start: 60.0,
+            step: 1.5,
+            min: 20.0,
+            max: 100.0,
+        }
+    }
+}
+
+impl PollutionSpikes {
+    fn pm25() -> Self {
+        Self {
+            baseline: 12.0,
+            noise: 2.0,
+            spike_probability: 0.02,
+            spike_magnitude: 80.0,
+            decay: 0.7,
+        }
+    }
+
+    fn co2() -> Self {
+        Self {
+            baseline: 420.0,
+            noise: 10.0,
+            spike_probability: 0.02,
+            spike_magnitude: 300.0,
+            decay: 0.8,
+        }
+    }
+}
+
+impl Default for SensorProfile {
+    fn default() -> Self {
+        Self::preset("rural").expect("rural preset exists")
+    }
+}
+
+impl SensorProfile {
+    /// Named profiles that can be selected instead of spelling out every parameter.
+    pub fn preset(name: &str) -> Option<Self> {
+        let rural = Self {
+            temperature: DiurnalCycle::default(),
+            humidity: RandomWalk::default(),
+            pm25: PollutionSpikes::pm25(),
+            co2: PollutionSpikes::co2(),
+            fault: None,
+        };
+
+        match name {
+            "rural" => Some(rural),
+            "urban" => Some(Self {
+                temperature: DiurnalCycle {
+                    mean: 21.0,
+                    amplitude: 5.0,
+                    ..DiurnalCycle::default()
+                },
+                pm25: PollutionSpikes {
+                    baseline: 35.0,
+                    spike_probability: 0.08,
+                    spike_magnitude: 150.0,
+                    ..PollutionSpikes::pm25()
+                },
+                co2: PollutionSpikes {
+                    baseline: 480.0,
+                    spike_probability: 0.08,
+                    ..PollutionSpikes::co2()
+                },
+                ..rural
+            }),
+            "indoor" => Some(Self {
+                temperature: DiurnalCycle {
+                    mean: 21.5,
+                    amplitude: 1.0,
+                    noise: 0.1,
+                    ..DiurnalCycle::default()
+                },
+                humidity: RandomWalk {
+                    start: 45.0,
+                    step: 0.5,
+                    min: 30.0,
+                    max: 60.0,
+                },
+                co2: PollutionSpikes {
+                    baseline: 700.0,
+                    spike_magnitude: 800.0,
+                    ..PollutionSpikes::co2()
+                },
+                ..rural
+            }),
+            "stuck" => Some(Self {
+                fault: Some(SensorFault::StuckAt { after_samples: 10 }),
+                ..rural
+            }),
+            "drifting" => Some(Self {
+                fault: Some(SensorFault::Drift { per_hour: 0.5 }),
+                ..rural
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), SimulationError> {
+        let invalid = |message: &str| Err(SimulationError::InvalidConfig(message.to_string()));
+        let (temperature, humidity) = (&self.temperature, &self.humidity);
+        let mut values = vec![
+            temperature.mean,
+            temperature.amplitude,
+            temperature.peak_hour,
+            temperature.noise,
+            humidity.start,
+            humidity.step,
+            humidity.min,
+            humidity.max,
+        ];
+        for spikes in [&self.pm25, &self.co2] {
+            values.extend([
+                spikes.baseline,
+                spikes.noise,
+                spikes.spike_probability,
+                spikes.spike_magnitude,
+                spikes.decay,
+            ]);
+        }
+        if let Some(SensorFault::Drift { per_hour }) = self.fault {
+            values.push(per_hour);
+        }
+        // TOML accepts `nan` and `inf`, which would poison every reading.
+        if !values.iter().all(|value| value.is_finite()) {
+            return invalid("sensor profile values must be finite numbers");
+        }
+        if self.humidity.min > self.humidity.max {
+            return invalid("humidity min must not exceed max");
+        }
+        for spikes in [&self.pm25, &self.co2] {
+            if !(0.0..=1.0).contains(&spikes.spike_probability) {
+                return invalid("spike_probability must be between 0 and 1");
+            }
+            if !(0.0..=1.0).contains(&spikes.decay) {
+                return invalid("decay must be between 0 and 1");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Produces a plausible stream of readings for one sensor.
+///
+/// All randomness comes from the generator's own seeded ChaCha RNG, so the
+/// same profile, seed and timestamps always yield the same readings on every
+/// platform and release.
+pub struct SensorGenerator {
+    profile: SensorProfile,
+    rng: ChaCha8Rng,
+    humidity: f64,
+    pm25_spike: f64,
+    co2_spike: f64,
+    samples: usize,
+    first_timestamp_ms: Option<u64>,
+    stuck: Option<SensorData>,
+}
+
+impl SensorGenerator {
+    pub fn new(profile: SensorProfile, seed: u64) -> Self {
+        Self {
+            humidity: profile.humidity.start,
+            profile,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            pm25_spike: 0.0,
+            co2_spike: 0.0,
+            samples: 0,
+            first_timestamp_ms: None,
+            stuck: None,
+        }
+    }
+
+    /// Produces the reading taken at `timestamp_ms` (Unix milliseconds).
+    pub fn sample(&mut self, timestamp_ms: u64) -> SensorData {
+        let first_timestamp_ms = *self.first_timestamp_ms.get_or_insert(timestamp_ms);
+        self.samples += 1;
+
+        if let Some(stuck) = &self.stuck {
+            return SensorData {
+                timestamp: timestamp_ms / 1000,
+                ..stuck.clone()
+            };
+        }
+
+        let hour_of_day = (timestamp_ms as f64 / MS_PER_HOUR) % 24.0;
+        let cycle = &self.profile.temperature;
+        let mut temperature = cycle.mean
+            + cycle.amplitude * (2.0 * PI * (hour_of_day - cycle.peak_hour) / 24.0).cos()
+            + cycle.noise * standard_normal(&mut self.rng);
+
+        let walk = &self.profile.humidity;
+        self.humidity =
+            (self.humidity + walk.step * standard_normal(&mut self.rng)).clamp(walk.min, walk.max);
+
+        let pm25 = Self::spiky(&self.profile.pm25, &mut self.pm25_spike, &mut self.rng);
+        let co2 = Self::spiky(&self.profile.co2, &mut self.co2_spike, &mut self.rng);
+
+        if let Some(SensorFault::Drift { per_hour }) = self.profile.fault {
+            let hours = (timestamp_ms - first_timestamp_ms) as f64 / MS_PER_HOUR;
+            temperature += per_hour * hours;
+        }
+
+        let data = SensorData {
+            pm25: pm25 as f32,
+            co2: co2 as f32,
+            temperature: temperature as f32,
+            humidity: self.humidity as f32,
+            timestamp: timestamp_ms / 1000,
+        };
+
+        if let Some(SensorFault::StuckAt { after_samples }) = self.profile.fault {
+            if self.samples >= after_samples {
+                self.stuck = Some(data.clone());
+            }
+        }
+        data
+    }
+
+    /// Baseline with noise plus occasional spikes that decay geometrically.
+    fn spiky(spikes: &PollutionSpikes, level: &mut f64, rng: &mut ChaCha8Rng) -> f64 {
+        *level *= spikes.decay;
+        if rng.gen_bool(spikes.spike_probability) {
+            *level += spikes.spike_magnitude * rng.gen_range(0.5..1.5);
+        }
+        (spikes.baseline + spikes.noise * standard_normal(rng) + *level).max(0.0)
+    }
+}
+
+/// Accepts either a preset name or a full sensor profile object.
+pub fn deserialize_sensor_profile<'de, D>(deserializer: D) -> Result<SensorProfile, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum SensorProfileInput {
+        Name(String),
+        Profile(SensorProfile),
+    }
+
+    match SensorProfileInput::deserialize(deserializer)? {
+        SensorProfileInput::Profile(profile) => Ok(profile),
+        SensorProfileInput::Name(name) => SensorProfile::preset(&name)
+            .ok_or_else(|| D::Error::custom(format!("unknown sensor profile '{}'", name))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Scenario;
+    use crate::simulation::ScenarioFormat;
+
+    #[test]
+    fn presets_are_valid() {
+        for name in ["rural", "urban", "indoor", "stuck", "drifting"] {
+            let profile = SensorProfile::preset(name).unwrap();
+            assert!(profile.validate().is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn non_finite_values_are_rejected() {
+        let mut profile = SensorProfile::default();
+        profile.humidity.min = f64::NAN;
+        assert!(profile.validate().is_err());
+
+        let mut profile = SensorProfile::default();
+        profile.co2.spike_magnitude = f64::INFINITY;
+        assert!(profile.validate().is_err());
+
+        let profile = SensorProfile {
+            fault: Some(SensorFault::Drift { per_hour: f64::NAN }),
+            ..SensorProfile::default()
+        };
+        assert!(profile.validate().is_err());
+    }
+
+    #[test]
+    fn toml_nan_bounds_fail_validation() {
+        let document = r#"
+            topology = "ring"
+            node_count = 3
+            block_count = 1
+            interval_ms = 100
+
+            [sensor_profile.humidity]
+            start = 50.0
+            step = 1.0
+            min = nan
+            max = 90.0
+        "#;
+        let error = Scenario::parse(document, ScenarioFormat::Toml).unwrap_err();
+        assert!(error.to_string().contains("finite"), "{}", error);
+    }
+
+    #[test]
+    fn same_seed_gives_same_readings() {
+        let mut a = SensorGenerator::new(SensorProfile::default(), 42);
+        let mut b = SensorGenerator::new(SensorProfile::default(), 42);
+        for i in 0..100 {
+            let timestamp_ms = 1_700_000_000_000 + i * 60_000;
+            assert_eq!(a.sample(timestamp_ms), b.sample(timestamp_ms));
+        }
+    }
+}
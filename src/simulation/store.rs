@@ -0,0 +1,218 @@
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File},
+    io::{BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+use uuid::Uuid;
+
+use crate::models::{RunRecord, SimulationEvent, SimulationReport, SweepReport};
+
+const DEFAULT_SIMULATION_DATA_DIR: &str = "data/simulations";
+const REPORT_FILE: &str = "report.json";
+const RUN_FILE: &str = "run.json";
+const EVENTS_FILE: &str = "events.jsonl";
+const SWEEPS_DIR: &str = "sweeps";
+
+/// On-disk storage for per-run simulation artifacts, one directory per run ID.
+#[derive(Debug, Clone)]
+pub struct RunStore {
+    dir: PathBuf,
+}
+
+impl Default for RunStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_SIMULATION_DATA_DIR)
+    }
+}
+
+impl RunStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Reads `SIMULATION_DATA_DIR` from the environment.
+    pub fn from_env() -> Self {
+        std::env::var("SIMULATION_DATA_DIR")
+            .map(Self::new)
+            .unwrap_or_default()
+    }
+
+    /// Directory holding one subdirectory per run.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn save_report(&self, report: &SimulationReport) -> anyhow::Result<()> {
+        self.write_json(&report.run_id, REPORT_FILE, report)
+    }
+
+    /// Loads the report for `run_id`, or `None` if there is no such run.
+    pub fn load_report(&self, run_id: &str) -> anyhow::Result<Option<SimulationReport>> {
+        self.read_json(run_id, REPORT_FILE)
+    }
+
+    pub fn save_run(&self, record: &RunRecord) -> anyhow::Result<()> {
+        self.write_json(&record.run_id, RUN_FILE, record)
+    }
+
+    pub fn load_run(&self, run_id: &str) -> anyhow::Result<Option<RunRecord>> {
+        self.read_json(run_id, RUN_FILE)
+    }
+
+    pub fn save_sweep(&self, report: &SweepReport) -> anyhow::Result<()> {
+        let path = self
+            .sweep_path(&report.sweep_id)
+            .ok_or_else(|| anyhow::anyhow!("invalid sweep id '{}'", report.sweep_id))?;
+        fs::create_dir_all(self.dir.join(SWEEPS_DIR))?;
+        fs::write(path, serde_json::to_vec_pretty(report)?)?;
+        Ok(())
+    }
+
+    /// Loads the report of a finished sweep, or `None` if there is no such
+    /// sweep.
+    pub fn load_sweep(&self, sweep_id: &str) -> anyhow::Result<Option<SweepReport>> {
+        let Some(path) = self.sweep_path(sweep_id) else {
+            return Ok(None);
+        };
+        match fs::read(path) {
+            Ok(contents) => Ok(Some(serde_json::from_slice(&contents)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Raw JSON Lines event log of `run_id`, or `None` if there is no such run.
+    pub fn load_events(&self, run_id: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let Some(dir) = self.run_dir(run_id) else {
+            return Ok(None);
+        };
+        match fs::read(dir.join(EVENTS_FILE)) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// IDs of all runs with a directory in the store.
+    pub fn run_ids(&self) -> anyhow::Result<Vec<String>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut run_ids = Vec::new();
+        for entry in entries {
+            let name = entry?.file_name();
+            if let Some(run_id) = name.to_str().filter(|name| Uuid::parse_str(name).is_ok()) {
+                run_ids.push(run_id.to_string());
+            }
+        }
+        Ok(run_ids)
+    }
+
+    /// Opens a fresh event log for `run_id`.
+    pub(crate) fn event_log(&self, run_id: &str) -> EventLog {
+        let file = self
+            .run_dir(run_id)
+            .ok_or_else(|| anyhow::anyhow!("invalid run id '{}'", run_id))
+            .and_then(|dir| {
+                fs::create_dir_all(&dir)?;
+                Ok(File::create(dir.join(EVENTS_FILE))?)
+            });
+        let writer = match file {
+            Ok(file) => Some(BufWriter::new(file)),
+            Err(e) => {
+                log::warn!("Not writing event log for simulation {}: {}", run_id, e);
+                None
+            }
+        };
+
+        EventLog {
+            writer,
+            digest: Sha256::new(),
+            events: 0,
+        }
+    }
+
+    fn write_json<T: serde::Serialize>(
+        &self,
+        run_id: &str,
+        file: &str,
+        value: &T,
+    ) -> anyhow::Result<()> {
+        let dir = self
+            .run_dir(run_id)
+            .ok_or_else(|| anyhow::anyhow!("invalid run id '{}'", run_id))?;
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(file), serde_json::to_vec_pretty(value)?)?;
+        Ok(())
+    }
+
+    fn read_json<T: serde::de::DeserializeOwned>(
+        &self,
+        run_id: &str,
+        file: &str,
+    ) -> anyhow::Result<Option<T>> {
+        let Some(dir) = self.run_dir(run_id) else {
+            return Ok(None);
+        };
+        match fs::read(dir.join(file)) {
+            Ok(contents) => Ok(Some(serde_json::from_slice(&contents)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Run IDs are UUIDs; anything else is rejected so IDs taken from URLs
+    /// can't point outside the data directory.
+    fn run_dir(&self, run_id: &str) -> Option<PathBuf> {
+        let run_id = Uuid::parse_str(run_id).ok()?;
+        Some(self.dir.join(run_id.to_string()))
+    }
+
+    fn sweep_path(&self, sweep_id: &str) -> Option<PathBuf> {
+        let sweep_id = Uuid::parse_str(sweep_id).ok()?;
+        Some(self.dir.join(SWEEPS_DIR).join(format!("{}.json", sweep_id)))
+    }
+}
+
+/// Appends a run's events to its JSON Lines log while hashing them, so two
+/// runs can be compared by digest alone.
+pub(crate) struct EventLog {
+    writer: Option<BufWriter<File>>,
+    digest: Sha256,
+    events: u64,
+}
+
+impl EventLog {
+    pub fn record(&mut self, event: &SimulationEvent) {
+        let mut line = serde_json::to_vec(event).expect("simulation events serialize");
+        line.push(b'\n');
+        self.digest.update(&line);
+        self.events += 1;
+
+        if let Some(writer) = &mut self.writer {
+            if let Err(e) = writer.write_all(&line) {
+                log::warn!("Stopped writing simulation event log: {}", e);
+                self.writer = None;
+            }
+        }
+    }
+
+    /// Flushes the log and returns the number of events and their digest.
+    pub fn finish(self) -> (u64, String) {
+        if let Some(mut writer) = self.writer {
+            if let Err(e) = writer.flush() {
+                log::warn!("Failed to flush simulation event log: {}", e);
+            }
+        }
+        let digest = self
+            .digest
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        (self.events, digest)
+    }
+}
@@ -0,0 +1,315 @@
+use chrono::Utc;
+use futures::stream::{self, StreamExt};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, RwLock},
+};
+use tokio::sync::watch;
+
+use super::compare::metrics;
+use super::node::DEFAULT_PARENT_COUNT;
+use super::{SimulationError, SimulationManager};
+use crate::models::{
+    SimulationConfig, SweepCombination, SweepMetricSummary, SweepReport, SweepRequest, SweepValues,
+};
+
+/// Most combinations a single sweep may run.
+const MAX_SWEEP_COMBINATIONS: usize = 1000;
+
+impl SweepValues {
+    /// The values of `parameter`, which must be whole numbers if `whole` is
+    /// set.
+    fn values(&self, parameter: &str, whole: bool) -> Result<Vec<f64>, SimulationError> {
+        let invalid =
+            |reason: &str| SimulationError::InvalidSweep(format!("{} {}", parameter, reason));
+        let values: Vec<f64> = match self {
+            SweepValues::List(values) => values.clone(),
+            &SweepValues::Range { min, max, step } => {
+                if !(min.is_finite() && max.is_finite() && step.is_finite())
+                    || step <= 0.0
+                    || min > max
+                {
+                    return Err(invalid("range needs min <= max and a positive step"));
+                }
+                let count = ((max - min) / step + 1e-9).floor() + 1.0;
+                if count > MAX_SWEEP_COMBINATIONS as f64 {
+                    return Err(invalid("range has too many values"));
+                }
+                // Rounded so steps such as 0.1 don't pick up float noise.
+                (0..count as usize)
+                    .map(|index| ((min + step * index as f64) * 1e9).round() / 1e9)
+                    .collect()
+            }
+        };
+        if values.is_empty() {
+            return Err(invalid("needs at least one value"));
+        }
+        if whole
+            && values
+                .iter()
+                .any(|value| value.fract() != 0.0 || *value < 0.0)
+        {
+            return Err(invalid("values must be whole numbers"));
+        }
+        Ok(values)
+    }
+}
+
+/// Values of a parameter, or just `default` when it is not swept.
+fn axis(
+    values: &Option<SweepValues>,
+    parameter: &str,
+    whole: bool,
+    default: f64,
+) -> Result<Vec<f64>, SimulationError> {
+    match values {
+        Some(values) => values.values(parameter, whole),
+        None => Ok(vec![default]),
+    }
+}
+
+impl SweepRequest {
+    /// Every combination of the swept values, with node count varying
+    /// slowest and loss rate fastest.
+    pub(crate) fn combinations(&self) -> Result<Vec<SweepCombination>, SimulationError> {
+        let base = &self.base;
+        let parameters = &self.parameters;
+        let node_counts = axis(
+            &parameters.node_count,
+            "node_count",
+            true,
+            base.node_count as f64,
+        )?;
+        let intervals = axis(
+            &parameters.interval_ms,
+            "interval_ms",
+            true,
+            base.interval_ms as f64,
+        )?;
+        let parent_counts = axis(
+            &parameters.parent_count,
+            "parent_count",
+            true,
+            base.parent_count.unwrap_or(DEFAULT_PARENT_COUNT) as f64,
+        )?;
+        let loss_rates = axis(
+            &parameters.loss_rate,
+            "loss_rate",
+            false,
+            base.link.loss_rate,
+        )?;
+
+        let total = node_counts.len() * intervals.len() * parent_counts.len() * loss_rates.len();
+        if total > MAX_SWEEP_COMBINATIONS {
+            return Err(SimulationError::InvalidSweep(format!(
+                "{} combinations exceed the limit of {}",
+                total, MAX_SWEEP_COMBINATIONS
+            )));
+        }
+        let mut combinations = Vec::with_capacity(total);
+        for &node_count in &node_counts {
+            for &interval_ms in &intervals {
+                for &parent_count in &parent_counts {
+                    for &loss_rate in &loss_rates {
+                        combinations.push(SweepCombination {
+                            node_count: node_count as usize,
+                            interval_ms: interval_ms as u64,
+                            parent_count: parent_count as usize,
+                            loss_rate,
+                            run_id: None,
+                            error: None,
+                            metrics: None,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(combinations)
+    }
+}
+
+impl SweepCombination {
+    /// `base` with this combination's values applied.
+    pub(crate) fn config(&self, base: &SimulationConfig) -> SimulationConfig {
+        let mut config = base.clone();
+        config.node_count = self.node_count;
+        config.interval_ms = self.interval_ms;
+        config.parent_count = Some(self.parent_count);
+        config.link.loss_rate = self.loss_rate;
+        config
+    }
+}
+
+impl SweepReport {
+    /// Recounts finished combinations and recomputes the summary over them.
+    fn summarize(&mut self) {
+        let finished: Vec<(usize, &BTreeMap<String, f64>)> = self
+            .combinations
+            .iter()
+            .enumerate()
+            .filter_map(|(index, combination)| Some((index, combination.metrics.as_ref()?)))
+            .collect();
+        self.finished_runs = finished.len();
+        let Some(&(_, first)) = finished.first() else {
+            self.summary.clear();
+            return;
+        };
+
+        self.summary = first
+            .keys()
+            .map(|metric| {
+                let values: Vec<(usize, f64)> = finished
+                    .iter()
+                    .filter_map(|&(index, metrics)| Some((index, *metrics.get(metric)?)))
+                    .collect();
+                let (min_combination, min) = values
+                    .iter()
+                    .copied()
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .expect("first finished combination has every metric");
+                let (max_combination, max) = values
+                    .iter()
+                    .copied()
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                    .expect("first finished combination has every metric");
+                SweepMetricSummary {
+                    metric: metric.clone(),
+                    min,
+                    max,
+                    mean: values.iter().map(|&(_, value)| value).sum::<f64>() / values.len() as f64,
+                    min_combination,
+                    max_combination,
+                }
+            })
+            .collect();
+    }
+
+    /// One row per combination with its parameter values and metrics.
+    pub fn to_csv(&self) -> String {
+        let metrics: Vec<&String> = self
+            .combinations
+            .iter()
+            .find_map(|combination| combination.metrics.as_ref())
+            .map(|metrics| metrics.keys().collect())
+            .unwrap_or_default();
+
+        let mut csv =
+            String::from("combination,node_count,interval_ms,parent_count,loss_rate,run_id,error");
+        for metric in &metrics {
+            let _ = write!(csv, ",{}", metric);
+        }
+        csv.push('\n');
+        for (index, combination) in self.combinations.iter().enumerate() {
+            let _ = write!(
+                csv,
+                "{},{},{},{},{},{},\"{}\"",
+                index,
+                combination.node_count,
+                combination.interval_ms,
+                combination.parent_count,
+                combination.loss_rate,
+                combination.run_id.as_deref().unwrap_or(""),
+                combination
+                    .error
+                    .as_deref()
+                    .unwrap_or("")
+                    .replace('"', "\"\""),
+            );
+            for metric in &metrics {
+                match combination
+                    .metrics
+                    .as_ref()
+                    .and_then(|metrics| metrics.get(*metric))
+                {
+                    Some(value) => {
+                        let _ = write!(csv, ",{}", value);
+                    }
+                    None => csv.push(','),
+                }
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// Runs a sweep's combinations, `parallelism` at a time, filling in `report`
+/// as they finish, then saves the report. Once `stop` is signalled the
+/// remaining combinations are skipped.
+pub(crate) async fn run_sweep(
+    manager: SimulationManager,
+    report: Arc<RwLock<SweepReport>>,
+    stop: watch::Receiver<bool>,
+) {
+    let (base, combinations, parallelism) = {
+        let report = report.read().unwrap();
+        (
+            report.base.clone(),
+            report.combinations.len(),
+            report.parallelism,
+        )
+    };
+    stream::iter(0..combinations)
+        .for_each_concurrent(parallelism, |index| {
+            run_combination(&manager, &report, &base, index, &stop)
+        })
+        .await;
+
+    let report = {
+        let mut report = report.write().unwrap();
+        report.is_running = false;
+        report.end_time = Some(Utc::now());
+        report.summarize();
+        report.clone()
+    };
+    if let Err(e) = manager.store.save_sweep(&report) {
+        log::warn!("Failed to save simulation sweep {}: {}", report.sweep_id, e);
+    }
+}
+
+async fn run_combination(
+    manager: &SimulationManager,
+    report: &RwLock<SweepReport>,
+    base: &SimulationConfig,
+    index: usize,
+    stop: &watch::Receiver<bool>,
+) {
+    if *stop.borrow() {
+        return;
+    }
+    let config = report.read().unwrap().combinations[index].config(base);
+    let run_id = match manager.start_with(config) {
+        Ok(run_id) => run_id,
+        Err(e) => {
+            report.write().unwrap().combinations[index].error = Some(e.to_string());
+            return;
+        }
+    };
+    report.write().unwrap().combinations[index].run_id = Some(run_id.clone());
+    // The sweep may have been stopped while the run was starting.
+    if *stop.borrow() {
+        let _ = manager.stop(&run_id);
+    }
+
+    let result = match manager.wait(&run_id).await {
+        Ok(()) => manager
+            .report(&run_id)
+            .map_err(|e| e.to_string())
+            .and_then(|run| run.ok_or_else(|| "run did not write a report".to_string())),
+        Err(e) => Err(e.to_string()),
+    };
+    let mut report = report.write().unwrap();
+    match result {
+        Ok(run) => {
+            report.combinations[index].metrics = Some(
+                metrics(&run)
+                    .into_iter()
+                    .map(|(metric, value)| (metric.to_string(), value))
+                    .collect(),
+            );
+            report.summarize();
+        }
+        Err(e) => report.combinations[index].error = Some(e),
+    }
+}
@@ -0,0 +1,345 @@
+use rand::{seq::SliceRandom, Rng};
+use serde::{de::Error as _, Deserialize, Deserializer};
+use std::collections::{BTreeSet, VecDeque};
+
+use super::SimulationError;
+use crate::models::{SimulationGraph, Topology};
+
+/// Most links a simulated topology may have. Every block is gossiped over
+/// every link, so this bounds the messages queued per block as well as the
+/// adjacency lists, whatever the node limit is.
+const MAX_TOPOLOGY_LINKS: usize = 100_000;
+
+impl Topology {
+    /// Parses the parameterless topologies from their bare name, so configs
+    /// written as `"topology": "ring"` keep working.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "mesh" | "full_mesh" => Some(Topology::FullMesh),
+            "ring" => Some(Topology::Ring),
+            "star" => Some(Topology::Star { hub: 0 }),
+            "line" => Some(Topology::Line),
+            _ => None,
+        }
+    }
+
+    pub fn validate(&self, node_count: usize) -> Result<(), SimulationError> {
+        let invalid = |message: String| Err(SimulationError::InvalidConfig(message));
+        if node_count == 0 {
+            return invalid("node_count must be at least 1".to_string());
+        }
+        let parameters = match *self {
+            Topology::FullMesh | Topology::Ring | Topology::Line => Ok(()),
+            Topology::Star { hub } if hub >= node_count => invalid(format!(
+                "star hub {} is out of range for {} nodes",
+                hub, node_count
+            )),
+            Topology::Star { .. } => Ok(()),
+            Topology::ErdosRenyi { p } if !(0.0..=1.0).contains(&p) => {
+                invalid("erdos_renyi p must be between 0 and 1".to_string())
+            }
+            Topology::ErdosRenyi { .. } => Ok(()),
+            Topology::WattsStrogatz { k, beta } => {
+                if k < 2 || k % 2 != 0 {
+                    invalid("watts_strogatz k must be an even number of at least 2".to_string())
+                } else if k >= node_count {
+                    invalid(format!(
+                        "watts_strogatz k must be less than node_count ({})",
+                        node_count
+                    ))
+                } else if !(0.0..=1.0).contains(&beta) {
+                    invalid("watts_strogatz beta must be between 0 and 1".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+            Topology::BarabasiAlbert { m } => {
+                if m == 0 {
+                    invalid("barabasi_albert m must be at least 1".to_string())
+                } else if m >= node_count {
+                    invalid(format!(
+                        "barabasi_albert m must be less than node_count ({})",
+                        node_count
+                    ))
+                } else {
+                    Ok(())
+                }
+            }
+        };
+        parameters?;
+
+        let links = self.expected_links(node_count);
+        if links > MAX_TOPOLOGY_LINKS as f64 {
+            return invalid(format!(
+                "topology would have about {:.0} links for {} nodes, more than the limit of {}",
+                links, node_count, MAX_TOPOLOGY_LINKS
+            ));
+        }
+        Ok(())
+    }
+
+    /// Number of links the topology has, on average for random ones.
+    fn expected_links(&self, node_count: usize) -> f64 {
+        let n = node_count as f64;
+        match *self {
+            Topology::FullMesh => n * (n - 1.0) / 2.0,
+            Topology::Ring => n,
+            Topology::Star { .. } | Topology::Line => n - 1.0,
+            Topology::ErdosRenyi { p } => p * n * (n - 1.0) / 2.0,
+            Topology::WattsStrogatz { k, .. } => n * (k / 2) as f64,
+            Topology::BarabasiAlbert { m } => m as f64 * n,
+        }
+    }
+
+    /// Builds sorted adjacency lists for `node_count` nodes.
+    pub fn generate<R: Rng>(&self, node_count: usize, rng: &mut R) -> Vec<Vec<usize>> {
+        let mut adjacency = vec![BTreeSet::new(); node_count];
+
+        match *self {
+            Topology::FullMesh => {
+                for a in 0..node_count {
+                    for b in a + 1..node_count {
+                        link(&mut adjacency, a, b);
+                    }
+                }
+            }
+            Topology::Ring => {
+                for a in 0..node_count {
+                    link(&mut adjacency, a, (a + 1) % node_count);
+                }
+            }
+            Topology::Star { hub } => {
+                for node in 0..node_count {
+                    link(&mut adjacency, hub, node);
+                }
+            }
+            Topology::Line => {
+                for a in 1..node_count {
+                    link(&mut adjacency, a - 1, a);
+                }
+            }
+            Topology::ErdosRenyi { p } => {
+                for a in 0..node_count {
+                    for b in a + 1..node_count {
+                        if rng.gen_bool(p) {
+                            link(&mut adjacency, a, b);
+                        }
+                    }
+                }
+            }
+            Topology::WattsStrogatz { k, beta } => {
+                // Ring lattice with k/2 neighbours on each side, then rewire
+                // each lattice edge with probability beta.
+                for a in 0..node_count {
+                    for offset in 1..=k / 2 {
+                        link(&mut adjacency, a, (a + offset) % node_count);
+                    }
+                }
+                for a in 0..node_count {
+                    for offset in 1..=k / 2 {
+                        let b = (a + offset) % node_count;
+                        if !rng.gen_bool(beta) || !adjacency[a].contains(&b) {
+                            continue;
+                        }
+                        let candidates: Vec<usize> = (0..node_count)
+                            .filter(|&c| c != a && !adjacency[a].contains(&c))
+                            .collect();
+                        if let Some(&c) = candidates.choose(rng) {
+                            adjacency[a].remove(&b);
+                            adjacency[b].remove(&a);
+                            link(&mut adjacency, a, c);
+                        }
+                    }
+                }
+            }
+            Topology::BarabasiAlbert { m } => {
+                // Seed with a fully connected core of m + 1 nodes; every later
+                // node attaches to m distinct nodes picked proportionally to
+                // their degree.
+                let core = (m + 1).min(node_count);
+                let mut endpoints = Vec::new();
+                for a in 0..core {
+                    for b in a + 1..core {
+                        link(&mut adjacency, a, b);
+                        endpoints.extend([a, b]);
+                    }
+                }
+                for node in core..node_count {
+                    let mut targets = BTreeSet::new();
+                    while targets.len() < m {
+                        targets.insert(endpoints[rng.gen_range(0..endpoints.len())]);
+                    }
+                    for target in targets {
+                        link(&mut adjacency, node, target);
+                        endpoints.extend([node, target]);
+                    }
+                }
+            }
+        }
+
+        adjacency
+            .into_iter()
+            .map(|neighbours| neighbours.into_iter().collect())
+            .collect()
+    }
+}
+
+impl SimulationGraph {
+    pub fn from_adjacency(adjacency: &[Vec<usize>]) -> Self {
+        let edges: Vec<[usize; 2]> = adjacency
+            .iter()
+            .enumerate()
+            .flat_map(|(a, neighbours)| {
+                neighbours
+                    .iter()
+                    .filter(move |&&b| a < b)
+                    .map(move |&b| [a, b])
+            })
+            .collect();
+        let average_degree = if adjacency.is_empty() {
+            0.0
+        } else {
+            2.0 * edges.len() as f64 / adjacency.len() as f64
+        };
+
+        Self {
+            node_count: adjacency.len(),
+            degrees: adjacency.iter().map(Vec::len).collect(),
+            average_degree,
+            connected: is_connected(adjacency),
+            edges,
+        }
+    }
+}
+
+fn link(adjacency: &mut [BTreeSet<usize>], a: usize, b: usize) {
+    if a != b {
+        adjacency[a].insert(b);
+        adjacency[b].insert(a);
+    }
+}
+
+fn is_connected(adjacency: &[Vec<usize>]) -> bool {
+    if adjacency.is_empty() {
+        return true;
+    }
+    let mut visited = vec![false; adjacency.len()];
+    let mut queue = VecDeque::from([0]);
+    visited[0] = true;
+    while let Some(node) = queue.pop_front() {
+        for &peer in &adjacency[node] {
+            if !visited[peer] {
+                visited[peer] = true;
+                queue.push_back(peer);
+            }
+        }
+    }
+    visited.into_iter().all(|seen| seen)
+}
+
+/// Accepts either a bare topology name or a tagged topology object.
+pub fn deserialize_topology<'de, D>(deserializer: D) -> Result<Topology, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum TopologyInput {
+        Name(String),
+        Spec(Topology),
+    }
+
+    match TopologyInput::deserialize(deserializer)? {
+        TopologyInput::Spec(topology) => Ok(topology),
+        TopologyInput::Name(name) => Topology::from_name(&name).ok_or_else(|| {
+            D::Error::custom(format!(
+                "unknown or parameterised topology '{}', use an object with a \"type\" field",
+                name
+            ))
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use serde_json::json;
+
+    fn graph(topology: Topology, node_count: usize) -> SimulationGraph {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        SimulationGraph::from_adjacency(&topology.generate(node_count, &mut rng))
+    }
+
+    #[test]
+    fn fixed_topologies_have_expected_edges() {
+        let mesh = graph(Topology::FullMesh, 6);
+        assert_eq!(mesh.edges.len(), 15);
+        assert!(mesh.degrees.iter().all(|&degree| degree == 5));
+
+        let ring = graph(Topology::Ring, 6);
+        assert_eq!(ring.edges.len(), 6);
+        assert!(ring.degrees.iter().all(|&degree| degree == 2));
+
+        let line = graph(Topology::Line, 6);
+        assert_eq!(line.edges.len(), 5);
+        assert!(line.connected);
+
+        let star = graph(Topology::Star { hub: 2 }, 6);
+        assert_eq!(star.edges.len(), 5);
+        assert_eq!(star.degrees[2], 5);
+
+        assert_eq!(graph(Topology::Ring, 1).edges.len(), 0);
+    }
+
+    #[test]
+    fn random_topologies_keep_their_edge_counts() {
+        assert_eq!(graph(Topology::ErdosRenyi { p: 0.0 }, 10).edges.len(), 0);
+        assert_eq!(graph(Topology::ErdosRenyi { p: 1.0 }, 10).edges.len(), 45);
+
+        let lattice = graph(Topology::WattsStrogatz { k: 4, beta: 0.0 }, 20);
+        assert!(lattice.degrees.iter().all(|&degree| degree == 4));
+        let rewired = graph(Topology::WattsStrogatz { k: 4, beta: 0.3 }, 20);
+        assert_eq!(rewired.edges.len(), 40);
+
+        let scale_free = graph(Topology::BarabasiAlbert { m: 2 }, 50);
+        assert_eq!(scale_free.edges.len(), 3 + 47 * 2);
+        assert!(scale_free.connected);
+    }
+
+    #[test]
+    fn same_seed_gives_same_topology() {
+        let topology = Topology::WattsStrogatz { k: 4, beta: 0.5 };
+        assert_eq!(graph(topology.clone(), 30).edges, graph(topology, 30).edges);
+    }
+
+    #[test]
+    fn validate_checks_parameters_and_link_cap() {
+        assert!(Topology::Ring.validate(0).is_err());
+        assert!(Topology::Star { hub: 3 }.validate(3).is_err());
+        assert!(Topology::ErdosRenyi { p: 1.5 }.validate(10).is_err());
+        assert!(Topology::WattsStrogatz { k: 3, beta: 0.1 }
+            .validate(10)
+            .is_err());
+        assert!(Topology::BarabasiAlbert { m: 10 }.validate(10).is_err());
+
+        assert!(Topology::FullMesh.validate(447).is_ok());
+        let error = Topology::FullMesh.validate(448).unwrap_err();
+        assert!(error.to_string().contains("100000"), "{}", error);
+        assert!(Topology::Ring.validate(100_000).is_ok());
+    }
+
+    #[test]
+    fn topologies_deserialize_from_names_and_objects() {
+        assert_eq!(
+            deserialize_topology(json!("mesh")).unwrap(),
+            Topology::FullMesh
+        );
+        assert_eq!(
+            deserialize_topology(json!({ "type": "erdos_renyi", "p": 0.1 })).unwrap(),
+            Topology::ErdosRenyi { p: 0.1 }
+        );
+        assert!(deserialize_topology(json!("erdos_renyi")).is_err());
+    }
+}
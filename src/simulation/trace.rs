@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use crate::models::TraceEvent;
+
+/// A recorded trace being replayed by a run.
+///
+/// Node 0 stands in for the node the trace was recorded on. Peers are given
+/// nodes 1, 2, … in the order they first connect; peers beyond the run's node
+/// count are left out.
+pub(crate) struct TraceReplay {
+    pub events: Vec<TraceEvent>,
+    /// Simulated IDs of the trace blocks replayed so far.
+    blocks: HashMap<String, u64>,
+    peers: HashMap<String, usize>,
+    node_count: usize,
+}
+
+impl TraceReplay {
+    pub fn new(events: Vec<TraceEvent>, node_count: usize) -> Self {
+        Self {
+            events,
+            blocks: HashMap::new(),
+            peers: HashMap::new(),
+            node_count,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Node standing in for a peer that connected, if there is one left.
+    pub fn connect(&mut self, peer: &str) -> Option<usize> {
+        if let Some(node) = self.node(peer) {
+            return Some(node);
+        }
+        let node = self.peers.len() + 1;
+        if node >= self.node_count {
+            return None;
+        }
+        self.peers.insert(peer.to_string(), node);
+        Some(node)
+    }
+
+    pub fn node(&self, peer: &str) -> Option<usize> {
+        self.peers.get(peer).copied()
+    }
+
+    /// Simulated IDs of recorded parents. Parents from before the recording
+    /// started were never replayed and are left out.
+    pub fn parents(&self, parents: &[String]) -> Vec<u64> {
+        parents
+            .iter()
+            .filter_map(|parent| self.blocks.get(parent).copied())
+            .collect()
+    }
+
+    pub fn block_replayed(&mut self, id: String, block: u64) {
+        self.blocks.insert(id, block);
+    }
+}
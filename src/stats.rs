@@ -0,0 +1,174 @@
+use chrono::{DateTime, Utc};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use tokio::time::interval;
+
+use crate::models::{ApiNetworkStats, NetworkInfo, NetworkMetrics, Peer};
+use crate::peers::BanList;
+use ecoblock_network::NetworkNode;
+
+const STATS_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+const BLOCK_RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Rebuilds `ApiNetworkStats` from the network node on every tick.
+///
+/// Keeps the state that a single snapshot cannot provide: when each peer was
+/// first seen connected, and recent block counts for the per-minute rate.
+struct StatsRefresher {
+    network_node: Arc<NetworkNode>,
+    ban_list: BanList,
+    connected_since: HashMap<String, DateTime<Utc>>,
+    block_counts: VecDeque<(Instant, usize)>,
+}
+
+impl StatsRefresher {
+    async fn snapshot(&mut self) -> ApiNetworkStats {
+        let stats = self.network_node.get_network_stats().await;
+        let peers = self.network_node.peer_discovery.get_peers().await;
+        let now = Utc::now();
+
+        self.connected_since.retain(|id, _| {
+            peers
+                .iter()
+                .any(|(peer_id, info)| info.is_connected && peer_id.0.to_string() == *id)
+        });
+
+        let api_peers: Vec<Peer> = peers
+            .iter()
+            .map(|(id, info)| {
+                let id = id.0.to_string();
+                let connected_at = if info.is_connected {
+                    *self.connected_since.entry(id.clone()).or_insert(now)
+                } else {
+                    now
+                };
+                let status = if self.ban_list.is_banned(&id) {
+                    "banned"
+                } else if info.is_connected {
+                    "connected"
+                } else {
+                    "disconnected"
+                };
+
+                Peer {
+                    id,
+                    address: info.address,
+                    connected_at,
+                    last_seen: DateTime::from_timestamp(info.last_seen as i64, 0).unwrap_or(now),
+                    status: status.to_string(),
+                }
+            })
+            .collect();
+
+        let connected: Vec<_> = peers.values().filter(|info| info.is_connected).collect();
+        let latencies: Vec<u64> = connected.iter().filter_map(|info| info.latency).collect();
+        let average_latency = if latencies.is_empty() {
+            0.0
+        } else {
+            latencies.iter().sum::<u64>() as f64 / latencies.len() as f64
+        };
+        let network_health = if peers.is_empty() {
+            0.0
+        } else {
+            connected.len() as f64 / peers.len() as f64
+        };
+
+        ApiNetworkStats {
+            info: NetworkInfo {
+                node_id: stats.node_id.0.to_string(),
+                peer_count: stats.peer_count,
+                block_count: stats.block_count,
+                uptime: stats.uptime,
+                status: "active".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                network_type: "mesh".to_string(),
+            },
+            peers: api_peers,
+            metrics: NetworkMetrics {
+                total_blocks: stats.block_count,
+                blocks_per_minute: self.blocks_per_minute(stats.block_count),
+                average_latency,
+                network_health,
+                active_peers: connected.len(),
+                total_peers: peers.len(),
+                active_connections: connected.len(),
+                messages_sent: 0,
+                messages_received: 0,
+                bytes_sent: 0,
+                bytes_received: 0,
+            },
+        }
+    }
+
+    fn blocks_per_minute(&mut self, block_count: usize) -> f64 {
+        let now = Instant::now();
+        self.block_counts.push_back((now, block_count));
+        while let Some(&(at, _)) = self.block_counts.front() {
+            if now.duration_since(at) <= BLOCK_RATE_WINDOW {
+                break;
+            }
+            self.block_counts.pop_front();
+        }
+
+        let (oldest_at, oldest_count) = self.block_counts[0];
+        let elapsed = now.duration_since(oldest_at).as_secs_f64();
+        if elapsed == 0.0 {
+            return 0.0;
+        }
+        block_count.saturating_sub(oldest_count) as f64 * 60.0 / elapsed
+    }
+}
+
+/// Keeps the shared network stats snapshot in sync with the network node so
+/// WebSocket `network_update` messages carry live data.
+pub fn spawn_stats_refresher(
+    network_node: Arc<NetworkNode>,
+    ban_list: BanList,
+    network_stats: Arc<RwLock<ApiNetworkStats>>,
+) {
+    let mut refresher = StatsRefresher {
+        network_node,
+        ban_list,
+        connected_since: HashMap::new(),
+        block_counts: VecDeque::new(),
+    };
+
+    tokio::spawn(async move {
+        let mut ticker = interval(STATS_REFRESH_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let snapshot = refresher.snapshot().await;
+            *network_stats.write().unwrap() = snapshot;
+        }
+    });
+}
+
+/// Nearest-rank percentile of an already sorted, non-empty slice.
+pub(crate) fn percentile(sorted: &[u64], p: f64) -> u64 {
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_uses_nearest_rank() {
+        let samples: Vec<u64> = (1..=10).collect();
+        assert_eq!(percentile(&samples, 50.0), 5);
+        assert_eq!(percentile(&samples, 90.0), 9);
+        assert_eq!(percentile(&samples, 99.0), 10);
+        assert_eq!(percentile(&samples, 100.0), 10);
+        assert_eq!(percentile(&samples, 0.0), 1);
+    }
+
+    #[test]
+    fn percentile_of_a_single_sample() {
+        assert_eq!(percentile(&[42], 1.0), 42);
+        assert_eq!(percentile(&[42], 99.0), 42);
+    }
+}
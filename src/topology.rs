@@ -0,0 +1,246 @@
+use chrono::Utc;
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::time::{interval, timeout_at, Instant};
+
+use crate::models::{ApiResponse, NetworkTopology, PeerInfo, TopologyEdge, TopologyNode};
+use ecoblock_network::NetworkNode;
+
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_PEER_API_PORT: u16 = 3000;
+const PEER_QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Peers queried at the same time during a crawl.
+const MAX_CONCURRENT_QUERIES: usize = 16;
+/// A crawl still running after this long keeps what it has found so far.
+const CRAWL_DEADLINE: Duration = Duration::from_secs(30);
+const MAX_CRAWL_DEPTH: usize = 3;
+const MAX_TOPOLOGY_NODES: usize = 500;
+
+/// Crawls the testnet mesh by asking each reachable peer's API for its own
+/// peer list, and keeps the last merged graph around for the API to serve.
+#[derive(Clone)]
+pub struct TopologyCrawler {
+    client: reqwest::Client,
+    peer_api_port: u16,
+    refresh_interval: Duration,
+    graph: Arc<RwLock<NetworkTopology>>,
+}
+
+impl TopologyCrawler {
+    /// Reads `TOPOLOGY_PEER_API_PORT` (port peers serve this API on) and
+    /// `TOPOLOGY_REFRESH_SECS` from the environment.
+    pub fn from_env() -> Self {
+        let peer_api_port = std::env::var("TOPOLOGY_PEER_API_PORT")
+            .ok()
+            .and_then(|port| port.parse().ok())
+            .unwrap_or(DEFAULT_PEER_API_PORT);
+        let refresh_interval = std::env::var("TOPOLOGY_REFRESH_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            // `interval` panics on a zero period.
+            .filter(|&secs| secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_REFRESH_INTERVAL);
+
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(PEER_QUERY_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            peer_api_port,
+            refresh_interval,
+            graph: Arc::new(RwLock::new(NetworkTopology::default())),
+        }
+    }
+
+    /// The graph from the last finished crawl, empty before the first one.
+    pub fn cached(&self) -> NetworkTopology {
+        self.graph.read().unwrap().clone()
+    }
+
+    pub async fn refresh(&self, network_node: &NetworkNode) {
+        let graph = self.crawl(network_node).await;
+        *self.graph.write().unwrap() = graph;
+    }
+
+    async fn crawl(&self, network_node: &NetworkNode) -> NetworkTopology {
+        let local_id = network_node.get_network_stats().await.node_id.0.to_string();
+        let mut nodes: HashMap<String, TopologyNode> = HashMap::new();
+        let mut edges: HashMap<(String, String), Vec<u64>> = HashMap::new();
+        let mut seen_edges: HashSet<(String, String)> = HashSet::new();
+        let mut queue = VecDeque::new();
+
+        nodes.insert(
+            local_id.clone(),
+            TopologyNode {
+                id: local_id.clone(),
+                address: None,
+                is_local: true,
+                reachable: true,
+                depth: 0,
+            },
+        );
+
+        let local_peers = network_node
+            .peer_discovery
+            .get_peers()
+            .await
+            .into_iter()
+            .map(|(id, info)| PeerInfo {
+                id: id.0.to_string(),
+                address: info.address.to_string(),
+                last_seen: info.last_seen,
+                is_connected: info.is_connected,
+                latency: info.latency,
+                reputation: None,
+            })
+            .collect::<Vec<_>>();
+        self.merge_peers(
+            &local_id,
+            0,
+            local_peers,
+            &mut nodes,
+            &mut edges,
+            &mut seen_edges,
+            &mut queue,
+        );
+
+        let deadline = Instant::now() + CRAWL_DEADLINE;
+        let mut queries = FuturesUnordered::new();
+        loop {
+            while queries.len() < MAX_CONCURRENT_QUERIES {
+                let Some((id, address, depth)) = queue.pop_front() else {
+                    break;
+                };
+                queries.push(async move { (id, depth, self.query_peers(&address).await) });
+            }
+            let (id, depth, peers) = match timeout_at(deadline, queries.next()).await {
+                Ok(Some(result)) => result,
+                Ok(None) => break,
+                Err(_) => {
+                    log::debug!(
+                        "Topology crawl hit its deadline with {} peers left to query",
+                        queries.len() + queue.len()
+                    );
+                    break;
+                }
+            };
+            let Some(peers) = peers else {
+                continue;
+            };
+            if let Some(node) = nodes.get_mut(&id) {
+                node.reachable = true;
+            }
+            self.merge_peers(
+                &id,
+                depth,
+                peers,
+                &mut nodes,
+                &mut edges,
+                &mut seen_edges,
+                &mut queue,
+            );
+        }
+
+        let edges = edges
+            .into_iter()
+            .map(|((source, target), latencies)| TopologyEdge {
+                source,
+                target,
+                latency: (!latencies.is_empty())
+                    .then(|| latencies.iter().sum::<u64>() / latencies.len() as u64),
+            })
+            .collect();
+
+        NetworkTopology {
+            nodes: nodes.into_values().collect(),
+            edges,
+            updated_at: Some(Utc::now()),
+        }
+    }
+
+    /// Adds `peers` as neighbours of `source`, queueing newly discovered
+    /// connected peers for the next crawl step.
+    #[allow(clippy::too_many_arguments)]
+    fn merge_peers(
+        &self,
+        source: &str,
+        depth: usize,
+        peers: Vec<PeerInfo>,
+        nodes: &mut HashMap<String, TopologyNode>,
+        edges: &mut HashMap<(String, String), Vec<u64>>,
+        seen_edges: &mut HashSet<(String, String)>,
+        queue: &mut VecDeque<(String, String, usize)>,
+    ) {
+        for peer in peers.into_iter().filter(|peer| peer.is_connected) {
+            if !nodes.contains_key(&peer.id) {
+                if nodes.len() >= MAX_TOPOLOGY_NODES {
+                    continue;
+                }
+                nodes.insert(
+                    peer.id.clone(),
+                    TopologyNode {
+                        id: peer.id.clone(),
+                        address: Some(peer.address.clone()),
+                        is_local: false,
+                        reachable: false,
+                        depth: depth + 1,
+                    },
+                );
+                if depth < MAX_CRAWL_DEPTH {
+                    queue.push_back((peer.id.clone(), peer.address.clone(), depth + 1));
+                }
+            }
+
+            // Each side of a link reports its own latency; keep one sample per
+            // direction and average them when the edge is built.
+            let key = if source < peer.id.as_str() {
+                (source.to_string(), peer.id.clone())
+            } else {
+                (peer.id.clone(), source.to_string())
+            };
+            let latencies = edges.entry(key).or_default();
+            if seen_edges.insert((source.to_string(), peer.id.clone())) {
+                latencies.extend(peer.latency);
+            }
+        }
+    }
+
+    async fn query_peers(&self, address: &str) -> Option<Vec<PeerInfo>> {
+        let address: SocketAddr = address.parse().ok()?;
+        let url = format!(
+            "http://{}/api/network/peers",
+            SocketAddr::new(address.ip(), self.peer_api_port)
+        );
+
+        let response = match self.client.get(&url).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                log::debug!("Topology query to {} failed: {}", url, e);
+                return None;
+            }
+        };
+
+        response
+            .json::<ApiResponse<Vec<PeerInfo>>>()
+            .await
+            .ok()
+            .and_then(|body| body.data)
+    }
+}
+
+/// Rebuilds the topology graph in the background on the crawler's interval.
+pub fn spawn_topology_refresher(network_node: Arc<NetworkNode>, crawler: TopologyCrawler) {
+    tokio::spawn(async move {
+        let mut ticker = interval(crawler.refresh_interval);
+        loop {
+            ticker.tick().await;
+            crawler.refresh(&network_node).await;
+        }
+    });
+}
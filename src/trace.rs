@@ -0,0 +1,305 @@
+use anyhow::{anyhow, bail};
+use chrono::Utc;
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{BufWriter, ErrorKind, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+use tokio::{
+    sync::watch,
+    time::{interval, Instant},
+};
+use uuid::Uuid;
+
+use crate::models::{TraceEvent, TraceEventKind, TraceStatus, TraceSummary};
+use ecoblock_network::NetworkNode;
+
+const DEFAULT_TRACE_DATA_DIR: &str = "data/traces";
+const TRACE_FILE_EXTENSION: &str = "jsonl";
+/// How often the recorder checks the node for new blocks and peer changes.
+const TRACE_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Traces recorded from the live network, one JSON Lines file per trace ID.
+#[derive(Debug, Clone)]
+pub struct TraceStore {
+    dir: PathBuf,
+}
+
+impl Default for TraceStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_TRACE_DATA_DIR)
+    }
+}
+
+impl TraceStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Reads `TRACE_DATA_DIR` from the environment.
+    pub fn from_env() -> Self {
+        std::env::var("TRACE_DATA_DIR")
+            .map(Self::new)
+            .unwrap_or_default()
+    }
+
+    /// Events of `trace_id`, or `None` if there is no such trace.
+    pub fn load(&self, trace_id: &str) -> anyhow::Result<Option<Vec<TraceEvent>>> {
+        let Some(contents) = self.load_raw(trace_id)? else {
+            return Ok(None);
+        };
+        let events = contents
+            .split(|&byte| byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(serde_json::from_slice)
+            .collect::<Result<_, _>>()?;
+        Ok(Some(events))
+    }
+
+    /// Raw JSON Lines file of `trace_id`, or `None` if there is no such trace.
+    pub fn load_raw(&self, trace_id: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let Some(path) = self.path(trace_id) else {
+            return Ok(None);
+        };
+        match fs::read(path) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Summaries of every trace in the store.
+    pub fn summaries(&self) -> anyhow::Result<Vec<TraceSummary>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut summaries = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(TRACE_FILE_EXTENSION) {
+                continue;
+            }
+            let Some(trace_id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            if let Some(events) = self.load(trace_id)? {
+                summaries.push(TraceSummary::new(trace_id, &events));
+            }
+        }
+        Ok(summaries)
+    }
+
+    fn create(&self, trace_id: &str) -> anyhow::Result<File> {
+        let path = self
+            .path(trace_id)
+            .ok_or_else(|| anyhow!("invalid trace id '{}'", trace_id))?;
+        fs::create_dir_all(&self.dir)?;
+        Ok(File::create(path)?)
+    }
+
+    /// Trace IDs are UUIDs; anything else is rejected so IDs taken from URLs
+    /// can't point outside the data directory.
+    fn path(&self, trace_id: &str) -> Option<PathBuf> {
+        let trace_id = Uuid::parse_str(trace_id).ok()?;
+        Some(
+            self.dir
+                .join(format!("{}.{}", trace_id, TRACE_FILE_EXTENSION)),
+        )
+    }
+}
+
+impl TraceSummary {
+    fn new(trace_id: &str, events: &[TraceEvent]) -> Self {
+        let blocks = events
+            .iter()
+            .filter(|event| matches!(event.kind, TraceEventKind::Block { .. }))
+            .count();
+        Self {
+            trace_id: trace_id.to_string(),
+            blocks,
+            peer_events: events.len() - blocks,
+            duration_ms: events.last().map_or(0, |event| event.at_ms),
+        }
+    }
+}
+
+/// Records the blocks and peer changes seen by the local node to a trace,
+/// so testnet incidents can be replayed in a simulation. One trace is
+/// recorded at a time.
+#[derive(Clone)]
+pub struct TraceRecorder {
+    store: TraceStore,
+    status: Arc<RwLock<TraceStatus>>,
+    stop: Arc<Mutex<Option<watch::Sender<bool>>>>,
+}
+
+impl TraceRecorder {
+    pub fn new(store: TraceStore) -> Self {
+        Self {
+            store,
+            status: Arc::new(RwLock::new(TraceStatus::default())),
+            stop: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn store(&self) -> &TraceStore {
+        &self.store
+    }
+
+    pub fn status(&self) -> TraceStatus {
+        self.status.read().unwrap().clone()
+    }
+
+    /// Starts recording what `network_node` sees to a new trace and returns
+    /// its ID. Blocks the node already had are left out.
+    pub fn start(&self, network_node: Arc<NetworkNode>) -> anyhow::Result<String> {
+        let mut stop = self.stop.lock().unwrap();
+        // The sender is closed once a recording ends on its own.
+        if stop.as_ref().is_some_and(|stop| !stop.is_closed()) {
+            bail!("A trace is already being recorded");
+        }
+
+        let trace_id = Uuid::new_v4().to_string();
+        let writer = BufWriter::new(self.store.create(&trace_id)?);
+        let (stop_tx, stop_rx) = watch::channel(false);
+        *stop = Some(stop_tx);
+        *self.status.write().unwrap() = TraceStatus {
+            recording: true,
+            trace_id: Some(trace_id.clone()),
+            started_at: Some(Utc::now()),
+            ..TraceStatus::default()
+        };
+        tokio::spawn(record(network_node, writer, self.status.clone(), stop_rx));
+        Ok(trace_id)
+    }
+
+    /// Stops the current recording and returns its trace ID.
+    pub fn stop(&self) -> anyhow::Result<String> {
+        let stop = self
+            .stop
+            .lock()
+            .unwrap()
+            .take()
+            .filter(|stop| !stop.is_closed())
+            .ok_or_else(|| anyhow!("No trace is being recorded"))?;
+        let _ = stop.send(true);
+
+        let mut status = self.status.write().unwrap();
+        status.recording = false;
+        status.stopped_at = Some(Utc::now());
+        Ok(status.trace_id.clone().unwrap_or_default())
+    }
+}
+
+async fn record(
+    network_node: Arc<NetworkNode>,
+    mut writer: BufWriter<File>,
+    status: Arc<RwLock<TraceStatus>>,
+    mut stop: watch::Receiver<bool>,
+) {
+    let started = Instant::now();
+    let mut seen_blocks: HashSet<String> = network_node
+        .block_cache
+        .read()
+        .await
+        .keys()
+        .cloned()
+        .collect();
+    let mut connected = HashSet::new();
+    let mut ticker = interval(TRACE_POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = stop.changed() => break,
+        }
+
+        let at_ms = started.elapsed().as_millis() as u64;
+        let events = poll(&network_node, &mut seen_blocks, &mut connected).await;
+        let result = events.into_iter().try_for_each(|kind| {
+            let is_block = matches!(kind, TraceEventKind::Block { .. });
+            let mut line = serde_json::to_vec(&TraceEvent { at_ms, kind })?;
+            line.push(b'\n');
+            writer.write_all(&line)?;
+
+            let mut status = status.write().unwrap();
+            if is_block {
+                status.blocks += 1;
+            } else {
+                status.peer_events += 1;
+            }
+            anyhow::Ok(())
+        });
+        if let Err(e) = result.and_then(|()| Ok(writer.flush()?)) {
+            log::warn!("Stopped recording trace: {}", e);
+            let mut status = status.write().unwrap();
+            status.recording = false;
+            status.stopped_at = Some(Utc::now());
+            return;
+        }
+    }
+    if let Err(e) = writer.flush() {
+        log::warn!("Failed to flush trace: {}", e);
+    }
+}
+
+/// Peer changes and new blocks since the last poll. Blocks are ordered by
+/// reading time so parents come before the blocks approving them.
+async fn poll(
+    network_node: &NetworkNode,
+    seen_blocks: &mut HashSet<String>,
+    connected: &mut HashSet<String>,
+) -> Vec<TraceEventKind> {
+    let mut events = Vec::new();
+
+    let mut peers: Vec<(String, String)> = network_node
+        .peer_discovery
+        .get_peers()
+        .await
+        .iter()
+        .filter(|(_, info)| info.is_connected)
+        .map(|(id, info)| (id.0.to_string(), info.address.to_string()))
+        .collect();
+    peers.sort();
+    let mut disconnected: Vec<String> = connected
+        .iter()
+        .filter(|peer| !peers.iter().any(|(id, _)| id == *peer))
+        .cloned()
+        .collect();
+    disconnected.sort();
+    for peer in disconnected {
+        connected.remove(&peer);
+        events.push(TraceEventKind::PeerDisconnected { peer });
+    }
+    for (peer, address) in peers {
+        if connected.insert(peer.clone()) {
+            events.push(TraceEventKind::PeerConnected { peer, address });
+        }
+    }
+
+    let cache = network_node.block_cache.read().await;
+    let mut blocks: Vec<_> = cache
+        .iter()
+        .filter(|(id, _)| !seen_blocks.contains(*id))
+        .collect();
+    blocks.sort_by(|(a_id, a), (b_id, b)| {
+        a.data
+            .data
+            .timestamp
+            .cmp(&b.data.data.timestamp)
+            .then(a_id.cmp(b_id))
+    });
+    for (id, block) in blocks {
+        seen_blocks.insert(id.clone());
+        events.push(TraceEventKind::Block {
+            id: id.clone(),
+            parents: block.data.parents.clone(),
+            data: block.data.data.clone(),
+        });
+    }
+    events
+}
@@ -1,20 +1,98 @@
 use axum::{
-    extract::{ws::WebSocket, WebSocketUpgrade},
+    extract::{
+        ws::{Message, WebSocket},
+        WebSocketUpgrade,
+    },
     response::Response,
 };
 use futures::{sink::SinkExt, stream::StreamExt};
-use serde_json::json;
+use serde_json::{json, Value};
 use std::{
-    collections::HashMap,
-    sync::{Arc, RwLock},
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex, RwLock},
     time::{Duration, Instant},
 };
-use tokio::time::interval;
+use tokio::{
+    sync::{
+        broadcast::{self, error::RecvError},
+        mpsc::{self, error::TrySendError},
+    },
+    time::interval,
+};
 use uuid::Uuid;
 
+use crate::block_filter::BlockFilter;
+use crate::handlers::block_info;
+use crate::models::{
+    ApiNetworkStats, BanEvent, BlockInfo, PeerEvent, SimulationUpdate, SimulationUpdateKind,
+    WsChannel, WsClientMessage,
+};
+use crate::peers::{BanList, PeerMonitor};
+use crate::simulation::SimulationManager;
+use ecoblock_core::SensorData;
+use ecoblock_network::NetworkNode;
+
+/// Channel messages buffered per connection before slow clients start
+/// missing them.
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+/// Messages waiting to be written to one socket before further channel
+/// messages for it are dropped.
+const OUTBOUND_QUEUE_CAPACITY: usize = 256;
+/// How often the block cache is checked for blocks received from peers.
+const BLOCK_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const NETWORK_UPDATE_INTERVAL: Duration = Duration::from_secs(2);
+/// Most recent channel messages kept for clients resuming with `last_seq`.
+const REPLAY_BUFFER_CAPACITY: usize = 1000;
+/// Most `block_created` and `propagation_complete` simulation updates sent
+/// per second, across all runs. Lifecycle updates are always sent.
+const SIMULATION_PROGRESS_PER_SECOND: usize = 20;
+
 #[derive(Clone)]
 pub struct WebSocketManager {
     connections: Arc<RwLock<HashMap<Uuid, WebSocketConnection>>>,
+    events: broadcast::Sender<ChannelMessage>,
+    /// Hashes of blocks already sent as `block_created`, so a block created
+    /// locally is not announced again when the block cache is polled. Only
+    /// hashes still in the block cache are kept.
+    announced_blocks: Arc<Mutex<HashSet<String>>>,
+    replay: Arc<Mutex<ReplayBuffer>>,
+}
+
+/// A serialized message published on a channel; each connection forwards it
+/// only if subscribed to that channel.
+#[derive(Clone)]
+struct ChannelMessage {
+    /// Unset for simulation progress, which is neither numbered nor kept for
+    /// replays so a fast run cannot push other channels out of the buffer.
+    seq: Option<u64>,
+    channel: WsChannel,
+    text: String,
+    /// Reading of the block a `block_created` message is about, checked
+    /// against each connection's block filter.
+    reading: Option<SensorData>,
+}
+
+/// Channel messages in sequence order, oldest first.
+struct ReplayBuffer {
+    next_seq: u64,
+    messages: VecDeque<ChannelMessage>,
+}
+
+/// What a connection has asked to receive.
+#[derive(Default)]
+struct Subscriptions {
+    channels: HashSet<WsChannel>,
+    block_filter: BlockFilter,
+}
+
+impl Subscriptions {
+    fn wants(&self, message: &ChannelMessage) -> bool {
+        self.channels.contains(&message.channel)
+            && message
+                .reading
+                .as_ref()
+                .is_none_or(|reading| self.block_filter.matches(reading))
+    }
 }
 
 struct WebSocketConnection {
@@ -26,6 +104,12 @@ impl WebSocketManager {
     pub fn new() -> Self {
         Self {
             connections: Arc::new(RwLock::new(HashMap::new())),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+            announced_blocks: Arc::new(Mutex::new(HashSet::new())),
+            replay: Arc::new(Mutex::new(ReplayBuffer {
+                next_seq: 1,
+                messages: VecDeque::with_capacity(REPLAY_BUFFER_CAPACITY),
+            })),
         }
     }
 
@@ -33,22 +117,12 @@ impl WebSocketManager {
         self.connections.read().unwrap().len()
     }
 
-    pub async fn handle_upgrade(
-        &self,
-        ws: WebSocketUpgrade,
-        network_stats: Arc<RwLock<crate::models::ApiNetworkStats>>,
-    ) -> Response {
+    pub async fn handle_upgrade(&self, ws: WebSocketUpgrade) -> Response {
         let manager = self.clone();
-        ws.on_upgrade(
-            move |socket| async move { manager.handle_socket(socket, network_stats).await },
-        )
+        ws.on_upgrade(move |socket| async move { manager.handle_socket(socket).await })
     }
 
-    async fn handle_socket(
-        &self,
-        socket: WebSocket,
-        network_stats: Arc<RwLock<crate::models::ApiNetworkStats>>,
-    ) {
+    async fn handle_socket(&self, socket: WebSocket) {
         let connection_id = Uuid::new_v4();
         let connection = WebSocketConnection {
             id: connection_id,
@@ -61,47 +135,110 @@ impl WebSocketManager {
         }
 
         let (mut sender, mut receiver) = socket.split();
-        let mut interval = interval(Duration::from_secs(2));
+        // Socket writes happen on their own task so a slow client never holds
+        // up reading its requests or draining its channel messages.
+        let (outbound, mut queue) = mpsc::channel(OUTBOUND_QUEUE_CAPACITY);
+        let writer = tokio::spawn(async move {
+            while let Some(message) = queue.recv().await {
+                if sender.send(message).await.is_err() {
+                    break;
+                }
+            }
+        });
+        let mut events = self.events.subscribe();
+        // Clients receive nothing until they subscribe to a channel.
+        let mut subscriptions = Subscriptions::default();
+        // Messages up to this sequence number were covered by a replay.
+        let mut replayed_through = 0;
+        let mut last_seen = self.latest_seq();
+        // First message this client missed, reported as a gap before the
+        // next message it does get.
+        let mut missed_from = None;
 
-        loop {
+        'connection: loop {
             tokio::select! {
                 msg = receiver.next() => {
-                    if let Some(msg) = msg {
-                        match msg {
-                            Ok(msg) => {
-                                if matches!(msg, axum::extract::ws::Message::Close(_)) {
-                                    break;
+                    let (reply, resume_after) = match msg {
+                        Some(Ok(Message::Text(text))) => handle_client_message(&text, &mut subscriptions),
+                        Some(Ok(Message::Binary(_))) => (error_reply(None, "Messages must be JSON text"), None),
+                        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                        // Pings are answered by axum.
+                        Some(Ok(_)) => continue,
+                    };
+                    // Replies and replays are never dropped, so wait for room
+                    // in the queue.
+                    let mut replies = vec![reply.to_string()];
+                    if let Some(last_seq) = resume_after {
+                        match self.replay_since(last_seq, &subscriptions) {
+                            Ok((messages, through)) => {
+                                replies.extend(messages);
+                                replayed_through = through;
+                                missed_from = None;
+                            }
+                            Err(e) => replies.push(error_reply(None, &e).to_string()),
+                        }
+                    }
+                    for reply in replies {
+                        if outbound.send(Message::Text(reply)).await.is_err() {
+                            break 'connection;
+                        }
+                    }
+                }
+
+                message = events.recv() => {
+                    match message {
+                        Ok(message) => {
+                            let Some(seq) = message.seq else {
+                                if !subscriptions.wants(&message) {
+                                    continue;
                                 }
-                                if let Ok(_text) = msg.to_text() {
+                                match outbound.try_send(Message::Text(message.text)) {
+                                    Ok(()) => {}
+                                    Err(TrySendError::Full(_)) => {
+                                        log::debug!("WebSocket {} dropped a simulation update, queue full", connection_id);
+                                    }
+                                    Err(TrySendError::Closed(_)) => break,
                                 }
+                                continue;
+                            };
+                            last_seen = seq;
+                            if seq <= replayed_through || !subscriptions.wants(&message) {
+                                continue;
                             }
-                            Err(_e) => {
-                                break;
+                            // A lag that only skipped unsequenced messages
+                            // leaves nothing to report.
+                            if let Some(from_seq) = missed_from.filter(|&from_seq| from_seq < seq) {
+                                match outbound.try_send(Message::Text(gap_notice(from_seq, seq - 1))) {
+                                    Ok(()) => {}
+                                    // Still behind; this message is missed too.
+                                    Err(TrySendError::Full(_)) => continue,
+                                    Err(TrySendError::Closed(_)) => break,
+                                }
+                            }
+                            missed_from = None;
+                            match outbound.try_send(Message::Text(message.text)) {
+                                Ok(()) => {}
+                                Err(TrySendError::Full(_)) => {
+                                    log::debug!("WebSocket {} dropped message {}, queue full", connection_id, seq);
+                                    missed_from = Some(seq);
+                                }
+                                Err(TrySendError::Closed(_)) => break,
                             }
                         }
-                    } else {
-                        break;
+                        Err(RecvError::Lagged(skipped)) => {
+                            log::debug!("WebSocket {} skipped {} channel messages", connection_id, skipped);
+                            if !subscriptions.channels.is_empty() {
+                                missed_from.get_or_insert(last_seen + 1);
+                            }
+                        }
+                        Err(RecvError::Closed) => break,
                     }
                 }
 
-                _ = interval.tick() => {
-                    let stats = {
-                        let guard = network_stats.read().unwrap();
-                        guard.clone()
-                    };
-
-                    let update = json!({
-                        "type": "network_update",
-                        "timestamp": chrono::Utc::now().to_rfc3339(),
-                        "data": stats
-                    });
-
-                    if let Err(_e) = sender.send(axum::extract::ws::Message::Text(update.to_string())).await {
-                        break;
-                    }
-                }
+                _ = outbound.closed() => break,
             }
         }
+        writer.abort();
 
         {
             let mut connections = self.connections.write().unwrap();
@@ -109,26 +246,508 @@ impl WebSocketManager {
         }
     }
 
-    pub async fn broadcast_block_created(&self, block: &crate::models::BlockInfo) {
-        let _message = json!({
+    /// Sends a new block to clients subscribed to the `blocks` channel. Each
+    /// block is announced once.
+    pub fn broadcast_block_created(&self, block: &BlockInfo) {
+        if !self
+            .announced_blocks
+            .lock()
+            .unwrap()
+            .insert(block.hash.clone())
+        {
+            return;
+        }
+        let message = json!({
             "type": "block_created",
+            "channel": WsChannel::Blocks,
             "timestamp": chrono::Utc::now().to_rfc3339(),
             "data": block
         });
+        self.publish(WsChannel::Blocks, message, Some(block.sensor_data.clone()));
     }
 
-    pub async fn broadcast_network_event(&self, event: &str, data: serde_json::Value) {
-        let _message = json!({
+    /// Sends a network event to clients subscribed to the `network` channel.
+    pub fn broadcast_network_event(&self, event: &str, data: Value) {
+        let message = json!({
             "type": "network_event",
+            "channel": WsChannel::Network,
             "event": event,
             "timestamp": chrono::Utc::now().to_rfc3339(),
             "data": data
         });
+        self.publish(WsChannel::Network, message, None);
+    }
+
+    /// Sends a peer connecting or disconnecting to clients subscribed to the
+    /// `peers` channel.
+    pub fn broadcast_peer_event(&self, event: &PeerEvent) {
+        let message = json!({
+            "type": "peer_event",
+            "channel": WsChannel::Peers,
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "data": event
+        });
+        self.publish(WsChannel::Peers, message, None);
+    }
+
+    /// Sends a ban list change to clients subscribed to the `alerts` channel.
+    pub fn broadcast_ban_event(&self, event: &BanEvent) {
+        let message = json!({
+            "type": "ban_event",
+            "channel": WsChannel::Alerts,
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "data": event
+        });
+        self.publish(WsChannel::Alerts, message, None);
+    }
+
+    /// Sends a simulation progress update to clients subscribed to the
+    /// `simulation` channel. These carry no `seq` and are not replayed.
+    pub fn broadcast_simulation_event(&self, update: &SimulationUpdate) {
+        let message = json!({
+            "type": "simulation_event",
+            "channel": WsChannel::Simulation,
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "data": update
+        });
+        let _ = self.events.send(ChannelMessage {
+            seq: None,
+            channel: WsChannel::Simulation,
+            text: message.to_string(),
+            reading: None,
+        });
+    }
+
+    /// Sends a network stats snapshot to clients subscribed to the `network`
+    /// channel.
+    pub fn broadcast_network_update(&self, stats: &ApiNetworkStats) {
+        let message = json!({
+            "type": "network_update",
+            "channel": WsChannel::Network,
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "data": stats
+        });
+        self.publish(WsChannel::Network, message, None);
+    }
+
+    /// Stamps `message` with the next sequence number, keeps it for replays
+    /// and sends it to every connection.
+    fn publish(&self, channel: WsChannel, mut message: Value, reading: Option<SensorData>) {
+        let mut replay = self.replay.lock().unwrap();
+        let seq = replay.next_seq;
+        replay.next_seq += 1;
+        message["seq"] = seq.into();
+        let message = ChannelMessage {
+            seq: Some(seq),
+            channel,
+            text: message.to_string(),
+            reading,
+        };
+        if replay.messages.len() == REPLAY_BUFFER_CAPACITY {
+            replay.messages.pop_front();
+        }
+        replay.messages.push_back(message.clone());
+        // Sent under the lock so every connection sees messages in sequence
+        // order. No receivers just means no client is connected.
+        let _ = self.events.send(message);
+    }
+
+    fn latest_seq(&self) -> u64 {
+        self.replay.lock().unwrap().next_seq - 1
+    }
+
+    /// Buffered messages after `last_seq` that `subscriptions` wants, preceded
+    /// by a gap notice if some of them have already left the buffer, and the
+    /// latest sequence number the replay covers.
+    fn replay_since(
+        &self,
+        last_seq: u64,
+        subscriptions: &Subscriptions,
+    ) -> Result<(Vec<String>, u64), String> {
+        let replay = self.replay.lock().unwrap();
+        let latest_seq = replay.next_seq - 1;
+        if last_seq > latest_seq {
+            return Err(format!(
+                "last_seq {} is ahead of the latest event {}; sequence numbers restart with the server",
+                last_seq, latest_seq
+            ));
+        }
+
+        let mut messages = Vec::new();
+        let oldest_seq = replay
+            .messages
+            .front()
+            .and_then(|message| message.seq)
+            .unwrap_or(replay.next_seq);
+        if last_seq + 1 < oldest_seq {
+            messages.push(gap_notice(last_seq + 1, oldest_seq - 1));
+        }
+        messages.extend(
+            replay
+                .messages
+                .iter()
+                .filter(|message| message.seq > Some(last_seq) && subscriptions.wants(message))
+                .map(|message| message.text.clone()),
+        );
+        Ok((messages, latest_seq))
+    }
+}
+
+/// Applies a client message to the connection's subscriptions and returns the
+/// acknowledgement, or an error if the message is invalid, along with the
+/// `last_seq` to replay missed messages after.
+fn handle_client_message(text: &str, subscriptions: &mut Subscriptions) -> (Value, Option<u64>) {
+    let message: WsClientMessage = match serde_json::from_str(text) {
+        Ok(message) => message,
+        Err(e) => return (error_reply(None, &format!("Invalid message: {}", e)), None),
+    };
+    match message {
+        WsClientMessage::Subscribe {
+            id,
+            channels,
+            filters,
+            last_seq,
+        } => {
+            if channels.is_empty() {
+                return (error_reply(id, "No channels given"), None);
+            }
+            if channels.contains(&WsChannel::Blocks) {
+                match BlockFilter::parse(&filters) {
+                    Ok(filter) => subscriptions.block_filter = filter,
+                    Err(e) => return (error_reply(id, &e), None),
+                }
+            } else if !filters.is_empty() {
+                return (
+                    error_reply(id, "Filters only apply to the blocks channel"),
+                    None,
+                );
+            }
+            subscriptions.channels.extend(&channels);
+            (ack(id, "subscribe", &channels, subscriptions), last_seq)
+        }
+        WsClientMessage::Unsubscribe { id, channels } => {
+            if channels.is_empty() {
+                return (error_reply(id, "No channels given"), None);
+            }
+            for channel in &channels {
+                subscriptions.channels.remove(channel);
+            }
+            if channels.contains(&WsChannel::Blocks) {
+                subscriptions.block_filter = BlockFilter::default();
+            }
+            (ack(id, "unsubscribe", &channels, subscriptions), None)
+        }
     }
 }
 
+fn ack(
+    id: Option<String>,
+    action: &str,
+    channels: &[WsChannel],
+    subscriptions: &Subscriptions,
+) -> Value {
+    let mut current: Vec<WsChannel> = subscriptions.channels.iter().copied().collect();
+    current.sort();
+    json!({
+        "type": "ack",
+        "id": id,
+        "action": action,
+        "channels": channels,
+        "subscriptions": current,
+        "filters": subscriptions.block_filter.conditions(),
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    })
+}
+
+/// Tells a client that the messages from `from_seq` to `to_seq` were not
+/// delivered to it; some may not have matched its subscriptions anyway.
+fn gap_notice(from_seq: u64, to_seq: u64) -> String {
+    json!({
+        "type": "gap",
+        "from_seq": from_seq,
+        "to_seq": to_seq,
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    })
+    .to_string()
+}
+
+fn error_reply(id: Option<String>, message: &str) -> Value {
+    json!({
+        "type": "error",
+        "id": id,
+        "message": message,
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    })
+}
+
+/// Forwards progress updates from every simulation run to WebSocket clients.
+///
+/// Runs going as fast as possible create blocks far quicker than clients can
+/// read them, so per-block updates beyond `SIMULATION_PROGRESS_PER_SECOND`
+/// are dropped; the run's report has the full picture.
+pub fn spawn_simulation_forwarder(
+    simulation: SimulationManager,
+    websocket_manager: WebSocketManager,
+) {
+    let mut updates = simulation.subscribe();
+
+    tokio::spawn(async move {
+        let mut window_start = Instant::now();
+        let mut sent = 0;
+        let mut dropped = 0;
+        loop {
+            match updates.recv().await {
+                Ok(update) => {
+                    if matches!(
+                        update.kind,
+                        SimulationUpdateKind::BlockCreated { .. }
+                            | SimulationUpdateKind::PropagationComplete { .. }
+                    ) {
+                        if window_start.elapsed() >= Duration::from_secs(1) {
+                            if dropped > 0 {
+                                log::debug!(
+                                    "Simulation forwarder dropped {} progress updates",
+                                    dropped
+                                );
+                            }
+                            window_start = Instant::now();
+                            sent = 0;
+                            dropped = 0;
+                        }
+                        if sent == SIMULATION_PROGRESS_PER_SECOND {
+                            dropped += 1;
+                            continue;
+                        }
+                        sent += 1;
+                    }
+                    websocket_manager.broadcast_simulation_event(&update);
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    log::debug!("Simulation forwarder skipped {} updates", skipped);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// Forwards peers connecting and disconnecting to WebSocket clients.
+pub fn spawn_peer_forwarder(peer_monitor: PeerMonitor, websocket_manager: WebSocketManager) {
+    let mut events = peer_monitor.subscribe();
+
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => websocket_manager.broadcast_peer_event(&event),
+                Err(RecvError::Lagged(skipped)) => {
+                    log::debug!("Peer forwarder skipped {} events", skipped);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// Forwards bans, unbans and expired bans to WebSocket clients.
+pub fn spawn_ban_forwarder(ban_list: BanList, websocket_manager: WebSocketManager) {
+    let mut events = ban_list.subscribe();
+
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => websocket_manager.broadcast_ban_event(&event),
+                Err(RecvError::Lagged(skipped)) => {
+                    log::debug!("Ban forwarder skipped {} events", skipped);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// Publishes the latest network stats on the `network` channel.
+pub fn spawn_network_updates(
+    network_stats: Arc<RwLock<ApiNetworkStats>>,
+    websocket_manager: WebSocketManager,
+) {
+    tokio::spawn(async move {
+        let mut interval = interval(NETWORK_UPDATE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let stats = network_stats.read().unwrap().clone();
+            websocket_manager.broadcast_network_update(&stats);
+        }
+    });
+}
+
+/// Announces blocks that reach the block cache from the network. Blocks
+/// cached before the forwarder starts are not announced.
+pub fn spawn_block_forwarder(network_node: Arc<NetworkNode>, websocket_manager: WebSocketManager) {
+    tokio::spawn(async move {
+        let mut interval = interval(BLOCK_POLL_INTERVAL);
+        let mut first_poll = true;
+        loop {
+            interval.tick().await;
+            let cache = network_node.block_cache.read().await;
+            if first_poll {
+                websocket_manager
+                    .announced_blocks
+                    .lock()
+                    .unwrap()
+                    .extend(cache.keys().cloned());
+                first_poll = false;
+                continue;
+            }
+            let mut blocks: Vec<BlockInfo> = {
+                let mut announced = websocket_manager.announced_blocks.lock().unwrap();
+                // Blocks are cached before they are announced, so hashes that
+                // left the cache are never needed again.
+                announced.retain(|hash| cache.contains_key(hash));
+                cache
+                    .iter()
+                    .filter(|(hash, _)| !announced.contains(*hash))
+                    .map(|(_, block)| block_info(block))
+                    .collect()
+            };
+            drop(cache);
+            // Oldest first, so parents are announced before their children.
+            blocks.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then(a.hash.cmp(&b.hash)));
+            for block in &blocks {
+                websocket_manager.broadcast_block_created(block);
+            }
+        }
+    });
+}
+
 impl Default for WebSocketManager {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscribed_to(channels: &[WsChannel]) -> Subscriptions {
+        Subscriptions {
+            channels: channels.iter().copied().collect(),
+            block_filter: BlockFilter::default(),
+        }
+    }
+
+    fn reading(temperature: f32) -> SensorData {
+        SensorData {
+            pm25: 10.0,
+            co2: 400.0,
+            temperature,
+            humidity: 50.0,
+            timestamp: 1_700_000_000_000,
+        }
+    }
+
+    fn seq_of(text: &str) -> Value {
+        serde_json::from_str::<Value>(text).unwrap()["seq"].clone()
+    }
+
+    #[test]
+    fn replay_resumes_after_last_seq() {
+        let manager = WebSocketManager::new();
+        for i in 0..5 {
+            manager.broadcast_network_event("block_sent", json!({ "i": i }));
+        }
+        let (messages, through) = manager
+            .replay_since(3, &subscribed_to(&[WsChannel::Network]))
+            .unwrap();
+        assert_eq!(through, 5);
+        let seqs: Vec<Value> = messages.iter().map(|text| seq_of(text)).collect();
+        assert_eq!(seqs, [json!(4), json!(5)]);
+    }
+
+    #[test]
+    fn replay_reports_evicted_messages_as_a_gap() {
+        let manager = WebSocketManager::new();
+        for i in 0..REPLAY_BUFFER_CAPACITY + 10 {
+            manager.broadcast_network_event("block_sent", json!({ "i": i }));
+        }
+        let (messages, through) = manager
+            .replay_since(0, &subscribed_to(&[WsChannel::Network]))
+            .unwrap();
+        assert_eq!(through, REPLAY_BUFFER_CAPACITY as u64 + 10);
+        let gap: Value = serde_json::from_str(&messages[0]).unwrap();
+        assert_eq!(gap["type"], "gap");
+        assert_eq!(gap["from_seq"], 1);
+        assert_eq!(gap["to_seq"], 10);
+        assert_eq!(messages.len(), REPLAY_BUFFER_CAPACITY + 1);
+        assert_eq!(seq_of(&messages[1]), json!(11));
+    }
+
+    #[test]
+    fn replay_rejects_last_seq_ahead_of_latest() {
+        let manager = WebSocketManager::new();
+        manager.broadcast_network_event("block_sent", json!({}));
+        assert!(manager
+            .replay_since(2, &subscribed_to(&[WsChannel::Network]))
+            .is_err());
+        assert!(manager
+            .replay_since(1, &subscribed_to(&[WsChannel::Network]))
+            .is_ok());
+    }
+
+    #[test]
+    fn replay_applies_channels_and_block_filters() {
+        let manager = WebSocketManager::new();
+        manager.publish(WsChannel::Blocks, json!({}), Some(reading(25.0)));
+        manager.publish(WsChannel::Blocks, json!({}), Some(reading(35.0)));
+        manager.broadcast_network_event("block_sent", json!({}));
+
+        let mut subscriptions = subscribed_to(&[WsChannel::Blocks]);
+        subscriptions.block_filter = BlockFilter::parse(&["temperature > 30".to_string()]).unwrap();
+        let (messages, through) = manager.replay_since(0, &subscriptions).unwrap();
+        assert_eq!(through, 3);
+        let seqs: Vec<Value> = messages.iter().map(|text| seq_of(text)).collect();
+        assert_eq!(seqs, [json!(2)]);
+    }
+
+    #[test]
+    fn simulation_updates_are_not_sequenced_or_replayed() {
+        let manager = WebSocketManager::new();
+        let mut events = manager.events.subscribe();
+        manager.broadcast_simulation_event(&SimulationUpdate {
+            run_id: "run".to_string(),
+            at_ms: 0,
+            kind: SimulationUpdateKind::PropagationComplete {
+                block: 1,
+                delay_ms: 5,
+            },
+        });
+        assert_eq!(manager.latest_seq(), 0);
+        let (messages, _) = manager
+            .replay_since(0, &subscribed_to(&[WsChannel::Simulation]))
+            .unwrap();
+        assert!(messages.is_empty());
+
+        let message = events.try_recv().unwrap();
+        assert_eq!(message.seq, None);
+        assert_eq!(seq_of(&message.text), Value::Null);
+    }
+
+    #[test]
+    fn subscribe_passes_last_seq_through() {
+        let mut subscriptions = Subscriptions::default();
+        let (reply, last_seq) = handle_client_message(
+            r#"{ "type": "subscribe", "channels": ["blocks"], "last_seq": 42 }"#,
+            &mut subscriptions,
+        );
+        assert_eq!(reply["type"], "ack");
+        assert_eq!(last_seq, Some(42));
+        assert!(subscriptions.channels.contains(&WsChannel::Blocks));
+
+        let (reply, last_seq) = handle_client_message(
+            r#"{ "type": "subscribe", "channels": ["network"], "filters": ["pm25 > 1"] }"#,
+            &mut subscriptions,
+        );
+        assert_eq!(reply["type"], "error");
+        assert_eq!(last_seq, None);
+    }
+}